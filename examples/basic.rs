@@ -10,10 +10,10 @@ fn main() {
     ctx.pause();
 
     for (i, color) in wheel.iter().enumerate() {
-        let i = (i + 1) as u32;
+        let i = (i + 1) as f32;
 
         ctx.fill_color(*color);
-        ctx.fill_rect(50 * i, 50 * i, 100, 100);
+        ctx.fill_rect(50.0 * i, 50.0 * i, 100.0, 100.0);
         ctx.pause();
     }
 
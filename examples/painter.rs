@@ -19,7 +19,7 @@ fn main() {
 
         on events::MouseMove => |state, ctx, e| {
             if state.painting {
-                ctx.fill_rect(e.0 as u32 - 5, e.1 as u32 - 5, 10, 10);
+                ctx.fill_rect(e.0 as f32 - 5.0, e.1 as f32 - 5.0, 10.0, 10.0);
             }
         };
 
@@ -42,31 +42,49 @@ fn main() {
             }
 
             ctx.fill_color(state.1[state.2]);
-            ctx.fill_rect(0, 0, 10, 10);
+            ctx.fill_rect(0.0, 0.0, 10.0, 10.0);
         };
    } */
 
-    let mut ctx = wcanvas::Canvas::new();
+    let mut ctx = kansas::Canvas::new();
     let state = Rc::new(RefCell::new((
         false,
         [0xFF0000, 0xFFFF00, 0x00FF00, 
          0x00FFFF, 0x0000FF, 0xFF00FF],
         0,
+        None,
+        (0.0, 0.0),
     )));
     
     let c = state.clone();
     ctx.on::< events::MouseMove>(Box::new(move |ctx, e| {
-        if c.borrow().0 {
-            ctx.fill_rect((e.0 as u32).saturating_sub(5), (e.1 as u32).saturating_sub(5), 10, 10);
+        let mut state = c.borrow_mut();
+        let (x, y) = (e.0 as f32, e.1 as f32);
+        state.4 = (x, y);
+
+        if state.0 {
+            let (last_x, last_y) = state.3.unwrap_or((x, y));
+
+            ctx.draw_line(last_x, last_y, x, y);
+            state.3 = Some((x, y));
         }
     }));
 
     let d = state.clone();
-    ctx.on::< events::MouseClick>(Box::new(move |_, (state, button)| {
-        if state == events::ElementState::Pressed {
-            d.borrow_mut().0 = true;
-        } else {
-            d.borrow_mut().0 = false;
+    ctx.on::< events::MouseClick>(Box::new(move |ctx, (state, button)| {
+        let mut d = d.borrow_mut();
+        let pressed = state == events::ElementState::Pressed;
+
+        match button {
+            // right click fills like a paint bucket
+            events::MouseButton::Right => if pressed {
+                let (x, y) = d.4;
+                ctx.flood_fill(x as i32, y as i32, d.1[d.2], 16);
+            },
+            _ => {
+                d.0 = pressed;
+                d.3 = None;
+            },
         }
     }));
 
@@ -84,10 +102,16 @@ fn main() {
         }
 
         ctx.fill_color(state.1[state.2]);
-        ctx.fill_rect(0, 0, 10, 10);
+        ctx.stroke_color(state.1[state.2]);
+        ctx.fill_rect(0.0, 0.0, 10.0, 10.0);
     }));
 
+    ctx.background(kansas::Background::solid(0xFFFFFF));
+    ctx.clear();
+
     ctx.fill_color(state.borrow().1[0]);
+    ctx.stroke_color(state.borrow().1[0]);
+    ctx.antialias(true);
     //ctx.fill_color([0u8, 0xFF, 0x00, 0x80]);
     ctx.pause();
 
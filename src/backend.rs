@@ -0,0 +1,117 @@
+//! render backends
+//!
+//! a `Context` does all of its drawing through a `Backend`
+
+use image::pixel_count;
+
+/// a surface that canvas pixels can be uploaded to
+///
+/// all pixel data is 8 bit rgba, in rows from top to bottom.
+pub trait Backend {
+    /// copy a `width` x `height` block of pixels onto the canvas at `(x, y)`
+    ///
+    /// the block is guaranteed to lie inside the canvas.
+    fn update_canvas(&mut self, x: u32, y: u32, width: u32, height: u32, data: &[[u8; 4]]);
+
+    /// change the dimensions of the canvas
+    fn resize(&mut self, width: u32, height: u32);
+
+    /// show the current contents of the canvas
+    fn present(&mut self);
+
+    /// read a `width` x `height` block of pixels back from the canvas at `(x, y)`
+    fn read_back(&self, x: u32, y: u32, width: u32, height: u32) -> Vec<[u8; 4]>;
+
+    /// whether transparent parts of a displayed canvas are shown over a checkerboard
+    fn show_checkerboard(&mut self, _show: bool) {}
+}
+
+/// canvas pixels kept in main memory
+pub struct Software {
+    width: u32,
+    height: u32,
+    data: Vec<[u8; 4]>,
+}
+
+impl Software {
+    /// create a buffer that starts out fully transparent, like the window
+    /// texture does
+    pub fn new(width: u32, height: u32) -> Self {
+        let mut buffer = Software { width: 0, height: 0, data: Vec::new() };
+        buffer.resize(width, height);
+        buffer
+    }
+}
+
+impl Backend for Software {
+    fn update_canvas(&mut self, x: u32, y: u32, width: u32, height: u32, data: &[[u8; 4]]) {
+        for j in 0..height {
+            let src = j as usize * width as usize;
+            let dst = (y + j) as usize * self.width as usize + x as usize;
+
+            self.data[dst .. dst + width as usize]
+                .copy_from_slice(&data[src .. src + width as usize]);
+        }
+    }
+
+    /// resize the buffer, keeping whatever was drawn in the overlapping area
+    /// and leaving the rest transparent
+    fn resize(&mut self, new_width: u32, new_height: u32) {
+        let mut data = Vec::with_capacity(pixel_count(new_width, new_height));
+
+        for j in 0..new_height {
+            for i in 0..new_width {
+                if i < self.width && j < self.height {
+                    data.push(self.data[j as usize * self.width as usize + i as usize]);
+                } else {
+                    data.push([0; 4]);
+                }
+            }
+        }
+
+        self.width = new_width;
+        self.height = new_height;
+        self.data = data;
+    }
+
+    fn present(&mut self) {}
+
+    fn read_back(&self, x: u32, y: u32, width: u32, height: u32) -> Vec<[u8; 4]> {
+        let mut out = Vec::with_capacity(pixel_count(width, height));
+
+        for j in y .. y + height {
+            let start = j as usize * self.width as usize + x as usize;
+            out.extend_from_slice(&self.data[start .. start + width as usize]);
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Backend, Software};
+
+    #[test]
+    fn software_read_back() {
+        let mut buffer = Software::new(3, 2);
+        assert_eq!(buffer.read_back(0, 0, 3, 2), vec![[0; 4]; 6]);
+
+        buffer.update_canvas(1, 0, 2, 2, &[[1, 0, 0, 0xFF], [2, 0, 0, 0xFF], [3, 0, 0, 0xFF], [4, 0, 0, 0xFF]]);
+        assert_eq!(buffer.read_back(1, 1, 2, 1), vec![[3, 0, 0, 0xFF], [4, 0, 0, 0xFF]]);
+        assert_eq!(buffer.read_back(0, 0, 1, 2), vec![[0; 4]; 2]);
+    }
+
+    #[test]
+    fn software_resize() {
+        let mut buffer = Software::new(2, 2);
+        buffer.update_canvas(0, 0, 2, 2, &[[1, 0, 0, 0xFF], [2, 0, 0, 0xFF], [3, 0, 0, 0xFF], [4, 0, 0, 0xFF]]);
+
+        // the overlapping area is kept, new space is transparent
+        buffer.resize(3, 1);
+        assert_eq!(buffer.read_back(0, 0, 3, 1), vec![[1, 0, 0, 0xFF], [2, 0, 0, 0xFF], [0; 4]]);
+
+        buffer.resize(1, 2);
+        assert_eq!(buffer.read_back(0, 0, 1, 2), vec![[1, 0, 0, 0xFF], [0; 4]]);
+    }
+}
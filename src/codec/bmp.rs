@@ -0,0 +1,260 @@
+//! uncompressed windows bitmaps
+
+use std::io::{self, Read, Write};
+
+use image::ImageData;
+use super::invalid;
+
+const FILE_HEADER: u32 = 14;
+const INFO_HEADER: u32 = 40;
+const V4_HEADER: u32 = 108;
+
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+const BI_ALPHABITFIELDS: u32 = 6;
+
+fn put_u16(out: &mut Vec<u8>, v: u16) {
+    out.extend(&[v as u8, (v >> 8) as u8]);
+}
+
+fn put_u32(out: &mut Vec<u8>, v: u32) {
+    out.extend(&[v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8]);
+}
+
+fn get_u16(data: &[u8], at: usize) -> io::Result<u16> {
+    data.get(at .. at + 2)
+        .map(|b| b[0] as u16 | (b[1] as u16) << 8)
+        .ok_or_else(|| invalid("truncated bmp header"))
+}
+
+fn get_u32(data: &[u8], at: usize) -> io::Result<u32> {
+    data.get(at .. at + 4)
+        .map(|b| b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24)
+        .ok_or_else(|| invalid("truncated bmp header"))
+}
+
+/// write an image as a bottom up bmp
+///
+/// opaque images are stored as 24 bit, anything else as 32 bit with an
+/// alpha mask.
+pub(crate) fn encode<W: Write>(img: &ImageData, mut w: W) -> io::Result<()> {
+    let alpha = img.pixels.iter().any(|p| p[3] != 0xFF);
+    let (header, bpp) = if alpha { (V4_HEADER, 4) } else { (INFO_HEADER, 3) };
+
+    let stride = (img.width * bpp + 3) & !3;
+    let offset = FILE_HEADER + header;
+    let size = offset + stride * img.height;
+
+    let mut out = Vec::with_capacity(size as usize);
+    out.extend(b"BM");
+    put_u32(&mut out, size);
+    put_u32(&mut out, 0);
+    put_u32(&mut out, offset);
+
+    put_u32(&mut out, header);
+    put_u32(&mut out, img.width);
+    put_u32(&mut out, img.height);
+    put_u16(&mut out, 1); // planes
+    put_u16(&mut out, bpp as u16 * 8);
+    put_u32(&mut out, if alpha { BI_BITFIELDS } else { BI_RGB });
+    put_u32(&mut out, stride * img.height);
+    put_u32(&mut out, 2835); // 72 dpi
+    put_u32(&mut out, 2835);
+    put_u32(&mut out, 0); // palette size
+    put_u32(&mut out, 0); // important colors
+
+    if alpha {
+        put_u32(&mut out, 0x00FF_0000);
+        put_u32(&mut out, 0x0000_FF00);
+        put_u32(&mut out, 0x0000_00FF);
+        put_u32(&mut out, 0xFF00_0000);
+        out.extend(b"BGRs"); // LCS_sRGB, little endian
+        out.extend(&[0; 48]); // endpoints and gamma, unused for srgb
+    }
+
+    for y in (0..img.height).rev() {
+        let start = out.len();
+        for p in img.row(y) {
+            out.extend(&[p[2], p[1], p[0]]);
+            if alpha {
+                out.push(p[3]);
+            }
+        }
+
+        while out.len() - start < stride as usize {
+            out.push(0);
+        }
+    }
+
+    w.write_all(&out)?;
+    w.flush()
+}
+
+/// extracts one channel from a packed pixel and scales it to 8 bits
+#[derive(Copy, Clone)]
+struct Mask {
+    shift: u32,
+    max: u64,
+}
+
+impl Mask {
+    fn new(mask: u32) -> Self {
+        if mask == 0 {
+            return Mask { shift: 0, max: 0 };
+        }
+
+        let shift = mask.trailing_zeros();
+        Mask { shift, max: (mask >> shift) as u64 }
+    }
+
+    fn get(&self, v: u32, default: u8) -> u8 {
+        if self.max == 0 {
+            return default;
+        }
+
+        let c = (v >> self.shift) as u64 & self.max;
+        ((c * 255 + self.max / 2) / self.max) as u8
+    }
+}
+
+/// decode an uncompressed bmp
+///
+/// handles palette images from 1 to 8 bits, 16, 24 and 32 bit images,
+/// and bitfield masks.
+pub(crate) fn decode<R: Read>(mut r: R) -> io::Result<ImageData> {
+    let mut data = Vec::new();
+    r.read_to_end(&mut data)?;
+
+    if !data.starts_with(b"BM") {
+        return Err(invalid("not a bmp file"));
+    }
+
+    let offset = get_u32(&data, 10)? as usize;
+    let header = get_u32(&data, 14)?;
+
+    let (width, height, bpp, compression, colors, palette_entry) = if header == 12 {
+        // os/2 core header
+        (get_u16(&data, 18)? as i32, get_u16(&data, 20)? as i16 as i32,
+         get_u16(&data, 24)?, BI_RGB, 0, 3)
+    } else if header >= INFO_HEADER {
+        (get_u32(&data, 18)? as i32, get_u32(&data, 22)? as i32,
+         get_u16(&data, 28)?, get_u32(&data, 30)?, get_u32(&data, 46)?, 4)
+    } else {
+        return Err(invalid("unknown bmp header"));
+    };
+
+    let top_down = height < 0;
+    let (width, height) = ((width as i64).abs() as u32, (height as i64).abs() as u32);
+
+    let mut masks_end = FILE_HEADER as usize + header as usize;
+    let masks = match compression {
+        BI_RGB => match bpp {
+            16 => [0x7C00, 0x03E0, 0x001F, 0],
+            _ => [0x00FF_0000, 0x0000_FF00, 0x0000_00FF, 0],
+        },
+        BI_BITFIELDS | BI_ALPHABITFIELDS => {
+            // masks either live inside a v2+ header, or directly after a v1 one
+            let start = FILE_HEADER as usize + INFO_HEADER as usize;
+            let count = if compression == BI_ALPHABITFIELDS || header >= 56 { 4 } else { 3 };
+            if header == INFO_HEADER {
+                masks_end += count * 4;
+            }
+
+            let mut m = [0; 4];
+            for i in 0..count {
+                m[i] = get_u32(&data, start + i * 4)?;
+            }
+            m
+        },
+        _ => return Err(invalid("compressed bmp files aren't supported")),
+    };
+
+    let palette = if bpp <= 8 {
+        let count = if colors == 0 { 1 << bpp } else { colors.min(256) as usize };
+
+        (0..count).map(|i| {
+            let at = masks_end + i * palette_entry;
+            data.get(at .. at + 3)
+                .map(|c| [c[2], c[1], c[0], 0xFF])
+                .ok_or_else(|| invalid("truncated bmp palette"))
+        }).collect::<io::Result<Vec<_>>>()?
+    } else {
+        Vec::new()
+    };
+
+    if bpp != 1 && bpp != 4 && bpp != 8 && bpp != 16 && bpp != 24 && bpp != 32 {
+        return Err(invalid("unsupported bmp bit depth"));
+    }
+
+    let stride = ((width as usize * bpp as usize + 31) / 32) * 4;
+    let len = stride.checked_mul(height as usize).ok_or_else(|| invalid("bmp image too large"))?;
+    let body = data.get(offset..).unwrap_or(&[]);
+    if body.len() < len {
+        return Err(invalid("truncated bmp pixel data"));
+    }
+
+    let channels = [Mask::new(masks[0]), Mask::new(masks[1]), Mask::new(masks[2]), Mask::new(masks[3])];
+    let mut pixels = Vec::with_capacity(width as usize * height as usize);
+
+    for y in 0..height as usize {
+        let row = if top_down { y } else { height as usize - 1 - y };
+        let row = &body[row * stride .. (row + 1) * stride];
+
+        for x in 0..width as usize {
+            let p = match bpp {
+                1 | 4 | 8 => {
+                    let bit = x * bpp as usize;
+                    let index = (row[bit / 8] >> (8 - bpp as usize - bit % 8)) & ((1 << bpp) - 1) as u8;
+                    palette.get(index as usize).cloned().unwrap_or([0, 0, 0, 0xFF])
+                },
+                24 => [row[x * 3 + 2], row[x * 3 + 1], row[x * 3], 0xFF],
+                _ => {
+                    let v = if bpp == 16 {
+                        row[x * 2] as u32 | (row[x * 2 + 1] as u32) << 8
+                    } else {
+                        let b = &row[x * 4 .. x * 4 + 4];
+                        b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24
+                    };
+
+                    [channels[0].get(v, 0), channels[1].get(v, 0),
+                     channels[2].get(v, 0), channels[3].get(v, 0xFF)]
+                },
+            };
+
+            pixels.push(p);
+        }
+    }
+
+    Ok(ImageData::from_pixels(width, height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, put_u16, put_u32};
+
+    /// a 24 bit bmp header with no pixel data
+    fn header(width: i32, height: i32) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend(b"BM");
+        put_u32(&mut out, 54);
+        put_u32(&mut out, 0);
+        put_u32(&mut out, 54);
+
+        put_u32(&mut out, 40);
+        put_u32(&mut out, width as u32);
+        put_u32(&mut out, height as u32);
+        put_u16(&mut out, 1);
+        put_u16(&mut out, 24);
+        out.extend(&[0; 24]);
+        out
+    }
+
+    #[test]
+    fn forged_sizes() {
+        for &(w, h) in &[(::std::i32::MIN, 1), (1, ::std::i32::MIN), (::std::i32::MIN, ::std::i32::MIN)] {
+            assert!(decode(&header(w, h)[..]).is_err());
+        }
+
+        assert_eq!(decode(&header(0, -1)[..]).unwrap().dimensions(), (0, 1));
+    }
+}
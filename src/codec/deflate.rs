@@ -0,0 +1,260 @@
+//! zlib compression, using lz77 and the fixed huffman table
+
+/// base match length for length codes 257 - 285
+pub(crate) const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31,
+    35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+
+/// extra bits for length codes 257 - 285
+pub(crate) const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2,
+    3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// base distance for distance codes 0 - 29
+pub(crate) const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193,
+    257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+
+/// extra bits for distance codes 0 - 29
+pub(crate) const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6,
+    7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+
+const WINDOW: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 64;
+const HASH_BITS: u32 = 15;
+
+/// crc32, as used by png chunks
+pub(crate) struct Crc32 {
+    table: [u32; 256],
+    value: u32,
+}
+
+impl Crc32 {
+    pub fn new() -> Self {
+        let mut table = [0; 256];
+
+        for (n, entry) in table.iter_mut().enumerate() {
+            let mut c = n as u32;
+            for _ in 0..8 {
+                c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+            }
+            *entry = c;
+        }
+
+        Crc32 { table, value: 0xFFFF_FFFF }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for &b in data {
+            self.value = self.table[((self.value ^ b as u32) & 0xFF) as usize] ^ (self.value >> 8);
+        }
+    }
+
+    pub fn finish(&self) -> u32 {
+        self.value ^ 0xFFFF_FFFF
+    }
+}
+
+/// adler32 checksum of a zlib stream
+pub(crate) fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+
+    // 5552 is the most bytes we can sum before `b` could overflow
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+
+    (b << 16) | a
+}
+
+/// writes bits least significant first, the way deflate expects them
+struct BitWriter {
+    out: Vec<u8>,
+    acc: u32,
+    nbits: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        BitWriter { out: Vec::new(), acc: 0, nbits: 0 }
+    }
+
+    fn bits(&mut self, value: u32, n: u32) {
+        self.acc |= value << self.nbits;
+        self.nbits += n;
+
+        while self.nbits >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.nbits -= 8;
+        }
+    }
+
+    /// huffman codes are packed starting from their most significant bit
+    fn code(&mut self, code: u32, len: u32) {
+        let mut rev = 0;
+        for i in 0..len {
+            rev |= ((code >> i) & 1) << (len - 1 - i);
+        }
+        self.bits(rev, len);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.nbits > 0 {
+            self.out.push(self.acc as u8);
+        }
+        self.out
+    }
+}
+
+/// write a literal / length symbol using the fixed huffman table
+fn fixed_literal(w: &mut BitWriter, sym: u32) {
+    match sym {
+        0 ..= 143 => w.code(0x30 + sym, 8),
+        144 ..= 255 => w.code(0x190 + sym - 144, 9),
+        256 ..= 279 => w.code(sym - 256, 7),
+        _ => w.code(0xC0 + sym - 280, 8),
+    }
+}
+
+fn fixed_match(w: &mut BitWriter, len: usize, dist: usize) {
+    let l = LENGTH_BASE.iter().rposition(|&b| b as usize <= len).unwrap();
+    fixed_literal(w, 257 + l as u32);
+    w.bits((len - LENGTH_BASE[l] as usize) as u32, LENGTH_EXTRA[l] as u32);
+
+    let d = DIST_BASE.iter().rposition(|&b| b as usize <= dist).unwrap();
+    w.code(d as u32, 5);
+    w.bits((dist - DIST_BASE[d] as usize) as u32, DIST_EXTRA[d] as u32);
+}
+
+fn hash(data: &[u8], i: usize) -> usize {
+    let v = (data[i] as u32) << 16 | (data[i + 1] as u32) << 8 | data[i + 2] as u32;
+    (v.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+}
+
+fn insert(data: &[u8], i: usize, head: &mut [usize], prev: &mut [usize]) {
+    if i + MIN_MATCH <= data.len() {
+        let h = hash(data, i);
+        prev[i] = head[h];
+        head[h] = i;
+    }
+}
+
+/// compress `data` into a single fixed huffman block
+fn fixed_block(data: &[u8]) -> Vec<u8> {
+    let mut w = BitWriter::new();
+    w.bits(1, 1); // final block
+    w.bits(1, 2); // fixed huffman
+
+    let mut head = vec![usize::max_value(); 1 << HASH_BITS];
+    let mut prev = vec![usize::max_value(); data.len()];
+
+    let mut i = 0;
+    while i < data.len() {
+        let mut best_len = 0;
+        let mut best_dist = 0;
+
+        if i + MIN_MATCH <= data.len() {
+            let mut candidate = head[hash(data, i)];
+            let mut chain = 0;
+            let max_len = MAX_MATCH.min(data.len() - i);
+
+            while candidate != usize::max_value() && i - candidate <= WINDOW && chain < MAX_CHAIN {
+                let len = data[candidate..].iter()
+                    .zip(&data[i .. i + max_len])
+                    .take_while(|&(a, b)| a == b)
+                    .count();
+
+                if len > best_len {
+                    best_len = len;
+                    best_dist = i - candidate;
+                    if len == max_len {
+                        break;
+                    }
+                }
+
+                candidate = prev[candidate];
+                chain += 1;
+            }
+        }
+
+        if best_len >= MIN_MATCH {
+            fixed_match(&mut w, best_len, best_dist);
+            for j in i .. i + best_len {
+                insert(data, j, &mut head, &mut prev);
+            }
+            i += best_len;
+        } else {
+            fixed_literal(&mut w, data[i] as u32);
+            insert(data, i, &mut head, &mut prev);
+            i += 1;
+        }
+    }
+
+    fixed_literal(&mut w, 256);
+    w.finish()
+}
+
+/// store `data` without compression
+fn stored_blocks(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / 65535 * 5 + 5);
+    let mut chunks = data.chunks(65535).peekable();
+
+    if chunks.peek().is_none() {
+        out.extend(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        let len = chunk.len() as u16;
+
+        out.push(last as u8);
+        out.extend(&[len as u8, (len >> 8) as u8, !len as u8, (!len >> 8) as u8]);
+        out.extend(chunk);
+    }
+
+    out
+}
+
+/// compress `data` into a zlib stream
+pub(crate) fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    let mut body = fixed_block(data);
+    if body.len() > data.len() + 5 {
+        body = stored_blocks(data);
+    }
+
+    let mut out = Vec::with_capacity(body.len() + 6);
+    out.extend(&[0x78, 0x01]);
+    out.extend(body);
+
+    let adler = adler32(data);
+    out.extend(&[(adler >> 24) as u8, (adler >> 16) as u8, (adler >> 8) as u8, adler as u8]);
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums() {
+        let mut crc = Crc32::new();
+        crc.update(b"123456789");
+
+        assert_eq!(crc.finish(), 0xCBF4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+}
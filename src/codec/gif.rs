@@ -0,0 +1,358 @@
+//! animated gif encoding
+
+use std::collections::HashMap;
+
+use image::ImageData;
+
+/// pixels with less alpha than this are written as transparent
+const ALPHA_CUTOFF: u8 = 0x80;
+
+/// one palette slot is always kept free for transparency
+const MAX_COLORS: usize = 255;
+
+fn put_u16(out: &mut Vec<u8>, v: u16) {
+    out.extend(&[v as u8, (v >> 8) as u8]);
+}
+
+/// write the file header, and ask viewers to loop forever
+pub(crate) fn write_header(out: &mut Vec<u8>, width: u16, height: u16) {
+    out.extend(b"GIF89a");
+    put_u16(out, width);
+    put_u16(out, height);
+    out.extend(&[0, 0, 0]); // no global color table, background 0, square pixels
+
+    out.extend(&[0x21, 0xFF, 11]);
+    out.extend(b"NETSCAPE2.0");
+    out.extend(&[3, 1, 0, 0, 0]);
+}
+
+pub(crate) fn write_trailer(out: &mut Vec<u8>) {
+    out.push(0x3B);
+}
+
+/// an axis aligned box of colors, for median cut
+struct ColorBox {
+    colors: Vec<([u8; 3], u32)>,
+}
+
+impl ColorBox {
+    /// channel with the largest spread, and that spread
+    fn widest(&self) -> (usize, u8) {
+        (0..3).map(|c| {
+            let min = self.colors.iter().map(|e| e.0[c]).min().unwrap();
+            let max = self.colors.iter().map(|e| e.0[c]).max().unwrap();
+            (c, max - min)
+        }).max_by_key(|&(_, range)| range).unwrap()
+    }
+
+    /// split at the weighted median of the widest channel
+    fn split(mut self) -> (ColorBox, ColorBox) {
+        let (channel, _) = self.widest();
+        self.colors.sort_by_key(|e| e.0[channel]);
+
+        let total: u64 = self.colors.iter().map(|e| e.1 as u64).sum();
+        let mut seen = 0;
+        let mut at = self.colors.len() - 1;
+        for (i, e) in self.colors.iter().enumerate() {
+            seen += e.1 as u64;
+            if seen * 2 >= total {
+                at = i + 1;
+                break;
+            }
+        }
+
+        let at = at.max(1).min(self.colors.len() - 1);
+        let rest = self.colors.split_off(at);
+        (self, ColorBox { colors: rest })
+    }
+
+    fn average(&self) -> [u8; 3] {
+        let mut sum = [0u64; 3];
+        let mut count = 0u64;
+
+        for &(c, n) in &self.colors {
+            for i in 0..3 {
+                sum[i] += c[i] as u64 * n as u64;
+            }
+            count += n as u64;
+        }
+
+        [((sum[0] + count / 2) / count) as u8,
+         ((sum[1] + count / 2) / count) as u8,
+         ((sum[2] + count / 2) / count) as u8]
+    }
+}
+
+/// reduce a color histogram to at most `MAX_COLORS` colors with median cut
+fn median_cut(histogram: &HashMap<[u8; 3], u32>) -> Vec<[u8; 3]> {
+    let mut boxes = vec![ColorBox { colors: histogram.iter().map(|(&c, &n)| (c, n)).collect() }];
+
+    while boxes.len() < MAX_COLORS {
+        let next = boxes.iter().enumerate()
+            .filter(|&(_, b)| b.colors.len() > 1)
+            .max_by_key(|&(_, b)| b.widest().1 as u64 * b.colors.len() as u64)
+            .map(|(i, _)| i);
+
+        match next {
+            Some(i) => {
+                let (a, b) = boxes.swap_remove(i).split();
+                boxes.push(a);
+                boxes.push(b);
+            },
+            None => break,
+        }
+    }
+
+    boxes.iter().map(ColorBox::average).collect()
+}
+
+fn nearest(palette: &[[u8; 3]], c: [u8; 3]) -> u8 {
+    let dist = |p: &[u8; 3]| {
+        (0..3).map(|i| {
+            let d = p[i] as i32 - c[i] as i32;
+            d * d
+        }).sum::<i32>()
+    };
+
+    palette.iter().enumerate().min_by_key(|&(_, p)| dist(p)).unwrap().0 as u8
+}
+
+/// palette indices and color table for one frame, with `keep` pixels transparent
+fn quantize(img: &ImageData, keep: &[bool]) -> (Vec<u8>, Vec<[u8; 3]>, Option<u8>) {
+    let visible = |i: usize| !keep[i] && img.pixels[i][3] >= ALPHA_CUTOFF;
+
+    let mut histogram = HashMap::new();
+    for (i, p) in img.pixels.iter().enumerate() {
+        if visible(i) {
+            *histogram.entry([p[0], p[1], p[2]]).or_insert(0) += 1;
+        }
+    }
+
+    let palette: Vec<[u8; 3]> = if histogram.len() <= MAX_COLORS {
+        let mut exact: Vec<_> = histogram.keys().cloned().collect();
+        exact.sort();
+        exact
+    } else {
+        median_cut(&histogram)
+    };
+
+    let transparent = if img.pixels.len() > histogram.values().sum::<u32>() as usize {
+        Some(palette.len() as u8)
+    } else {
+        None
+    };
+
+    let mut lookup: HashMap<[u8; 3], u8> = HashMap::new();
+    let indices = img.pixels.iter().enumerate().map(|(i, p)| {
+        if !visible(i) {
+            return transparent.unwrap();
+        }
+
+        let c = [p[0], p[1], p[2]];
+        *lookup.entry(c).or_insert_with(|| nearest(&palette, c))
+    }).collect();
+
+    (indices, palette, transparent)
+}
+
+/// lzw compress palette indices into gif data sub-blocks
+fn lzw(indices: &[u8], min_size: u8, out: &mut Vec<u8>) {
+    let clear = 1u32 << min_size;
+    let end = clear + 1;
+
+    let mut bits = Vec::new();
+    let (mut acc, mut nbits) = (0u32, 0u32);
+    let mut emit = |code: u32, size: u32, bits: &mut Vec<u8>| {
+        acc |= code << nbits;
+        nbits += size;
+        while nbits >= 8 {
+            bits.push(acc as u8);
+            acc >>= 8;
+            nbits -= 8;
+        }
+    };
+
+    let mut table: HashMap<(u32, u8), u32> = HashMap::new();
+    let mut next = end + 1;
+    let mut size = min_size as u32 + 1;
+
+    emit(clear, size, &mut bits);
+
+    let mut iter = indices.iter();
+    if let Some(&first) = iter.next() {
+        let mut prefix = first as u32;
+
+        for &k in iter {
+            if let Some(&code) = table.get(&(prefix, k)) {
+                prefix = code;
+                continue;
+            }
+
+            emit(prefix, size, &mut bits);
+
+            if next < 4096 {
+                table.insert((prefix, k), next);
+                next += 1;
+                if next > 1 << size && size < 12 {
+                    size += 1;
+                }
+            } else {
+                emit(clear, size, &mut bits);
+                table.clear();
+                next = end + 1;
+                size = min_size as u32 + 1;
+            }
+
+            prefix = k as u32;
+        }
+
+        emit(prefix, size, &mut bits);
+
+        // the decoder adds one more entry after reading the last code
+        if next == 1 << size && size < 12 {
+            size += 1;
+        }
+    }
+
+    emit(end, size, &mut bits);
+
+    if nbits > 0 {
+        bits.push(acc as u8);
+    }
+
+    out.push(min_size);
+    for block in bits.chunks(255) {
+        out.push(block.len() as u8);
+        out.extend(block);
+    }
+    out.push(0);
+}
+
+/// write one frame at `(x, y)` that lasts `delay` hundredths of a second
+pub(crate) fn write_frame(out: &mut Vec<u8>, x: u16, y: u16, img: &ImageData, keep: &[bool], delay: u16) {
+    let (indices, palette, transparent) = quantize(img, keep);
+
+    // color tables have a power of two size, at least 2
+    let slots = palette.len() + transparent.is_some() as usize;
+    let mut bits = 1;
+    while 1 << bits < slots {
+        bits += 1;
+    }
+
+    // graphic control extension: keep the previous frame underneath
+    out.extend(&[0x21, 0xF9, 4, 1 << 2 | transparent.is_some() as u8]);
+    put_u16(out, delay);
+    out.extend(&[transparent.unwrap_or(0), 0]);
+
+    out.push(0x2C);
+    put_u16(out, x);
+    put_u16(out, y);
+    put_u16(out, img.width as u16);
+    put_u16(out, img.height as u16);
+    out.push(0x80 | (bits - 1) as u8);
+
+    for i in 0 .. 1 << bits {
+        out.extend(palette.get(i).unwrap_or(&[0, 0, 0]));
+    }
+
+    lzw(&indices, (bits as u8).max(2), out);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lzw, quantize};
+    use image::ImageData;
+
+    /// a plain gif lzw decoder, reading the sub-blocks `lzw` writes
+    fn unlzw(data: &[u8]) -> Vec<u8> {
+        let min_size = data[0] as u32;
+        let mut bytes: Vec<u8> = Vec::new();
+        let mut pos = 1;
+        while data[pos] != 0 {
+            let len = data[pos] as usize;
+            bytes.extend(&data[pos + 1 .. pos + 1 + len]);
+            pos += 1 + len;
+        }
+        assert_eq!(pos, data.len() - 1);
+
+        let (clear, end) = (1 << min_size, (1 << min_size) + 1);
+        let mut table: Vec<Vec<u8>> = Vec::new();
+        let mut size = min_size + 1;
+        let mut prev: Option<Vec<u8>> = None;
+        let mut out = Vec::new();
+        let mut bit = 0;
+
+        loop {
+            let mut code = 0;
+            for i in 0..size {
+                code |= ((bytes[bit / 8] as u32 >> (bit % 8)) & 1) << i;
+                bit += 1;
+            }
+
+            if code == clear {
+                table = (0..clear).map(|i| vec![i as u8]).collect();
+                table.push(vec![]);
+                table.push(vec![]);
+                size = min_size + 1;
+                prev = None;
+                continue;
+            }
+            if code == end {
+                break;
+            }
+
+            let entry = match (table.get(code as usize).cloned(), prev.take()) {
+                (Some(e), Some(p)) => {
+                    let mut new = p;
+                    new.push(e[0]);
+                    table.push(new);
+                    e
+                },
+                (None, Some(p)) => {
+                    let mut new = p.clone();
+                    new.push(p[0]);
+                    table.push(new.clone());
+                    new
+                },
+                (Some(e), None) => e,
+                (None, None) => panic!("bad first code"),
+            };
+
+            if table.len() == 1 << size && size < 12 {
+                size += 1;
+            }
+
+            out.extend(&entry);
+            prev = Some(entry);
+        }
+
+        out
+    }
+
+    #[test]
+    fn lzw_roundtrip() {
+        let inputs: Vec<(u8, Vec<u8>)> = vec![
+            (2, vec![]),
+            (2, vec![1]),
+            (2, vec![0, 1, 2, 3, 0, 1, 2, 3, 0, 1, 2, 3, 3, 3, 3, 3, 3]),
+            (8, (0..100_000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8).collect()),
+            (4, (0..50_000u32).map(|i| (i / 7 % 16) as u8).collect()),
+        ];
+
+        for (min_size, data) in inputs {
+            let mut out = Vec::new();
+            lzw(&data, min_size, &mut out);
+            assert_eq!(unlzw(&out), data);
+        }
+    }
+
+    #[test]
+    fn unchanged_pixels_are_transparent() {
+        let img = ImageData::from_pixels(3, 1, vec![[1, 2, 3, 0xFF], [4, 5, 6, 0xFF], [0, 0, 0, 0]]);
+        let (indices, palette, transparent) = quantize(&img, &[false, true, false]);
+
+        assert_eq!(palette, vec![[1, 2, 3]]);
+        assert_eq!(transparent, Some(1));
+        assert_eq!(indices, vec![0, 1, 1]);
+    }
+}
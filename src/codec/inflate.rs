@@ -0,0 +1,264 @@
+//! zlib decompression
+//!
+//! a straightforward inflate, decoding huffman codes one bit at a time.
+//! images are small enough that simplicity wins over speed here.
+
+use std::io;
+
+use super::invalid;
+use super::deflate::{adler32, DIST_BASE, DIST_EXTRA, LENGTH_BASE, LENGTH_EXTRA};
+
+const MAX_BITS: usize = 15;
+
+/// order in which code length code lengths are stored
+const CLEN_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn bits(&mut self, n: u32) -> io::Result<u32> {
+        let mut v = 0;
+
+        for i in 0..n {
+            let byte = *self.data.get(self.pos).ok_or_else(|| invalid("truncated deflate stream"))?;
+            v |= ((byte as u32 >> self.bit) & 1) << i;
+
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.pos += 1;
+            }
+        }
+
+        Ok(v)
+    }
+
+    fn align(&mut self) {
+        if self.bit != 0 {
+            self.bit = 0;
+            self.pos += 1;
+        }
+    }
+}
+
+/// a canonical huffman code, stored as the number of codes of each length
+/// and the symbols sorted by code
+struct Huffman {
+    count: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut count = [0; MAX_BITS + 1];
+        for &len in lengths {
+            count[len as usize] += 1;
+        }
+        count[0] = 0;
+
+        let mut offsets = [0; MAX_BITS + 2];
+        for len in 1 ..= MAX_BITS {
+            offsets[len + 1] = offsets[len] + count[len];
+        }
+
+        let mut symbols = vec![0; lengths.len()];
+        for (sym, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = sym as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+
+        Huffman { count, symbols }
+    }
+
+    fn decode(&self, r: &mut BitReader) -> io::Result<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+
+        for len in 1 ..= MAX_BITS {
+            code |= r.bits(1)? as i32;
+            let count = self.count[len] as i32;
+
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(invalid("bad huffman code in deflate stream"))
+    }
+}
+
+fn fixed_tables() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    for (i, len) in lengths.iter_mut().enumerate() {
+        *len = match i {
+            0 ..= 143 => 8,
+            144 ..= 255 => 9,
+            256 ..= 279 => 7,
+            _ => 8,
+        };
+    }
+
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+fn dynamic_tables(r: &mut BitReader) -> io::Result<(Huffman, Huffman)> {
+    let nlen = r.bits(5)? as usize + 257;
+    let ndist = r.bits(5)? as usize + 1;
+    let ncode = r.bits(4)? as usize + 4;
+
+    let mut clens = [0u8; 19];
+    for &i in &CLEN_ORDER[..ncode] {
+        clens[i] = r.bits(3)? as u8;
+    }
+    let clen_code = Huffman::new(&clens);
+
+    let mut lengths = Vec::with_capacity(nlen + ndist);
+    while lengths.len() < nlen + ndist {
+        let sym = clen_code.decode(r)?;
+
+        let (value, repeat) = match sym {
+            0 ..= 15 => (sym as u8, 1),
+            16 => {
+                let prev = *lengths.last().ok_or_else(|| invalid("repeat with no previous length"))?;
+                (prev, 3 + r.bits(2)?)
+            },
+            17 => (0, 3 + r.bits(3)?),
+            _ => (0, 11 + r.bits(7)?),
+        };
+
+        for _ in 0..repeat {
+            lengths.push(value);
+        }
+    }
+
+    if lengths.len() > nlen + ndist {
+        return Err(invalid("too many code lengths in deflate stream"));
+    }
+
+    Ok((Huffman::new(&lengths[..nlen]), Huffman::new(&lengths[nlen..])))
+}
+
+fn inflate_block(r: &mut BitReader, out: &mut Vec<u8>, lit: &Huffman, dist: &Huffman) -> io::Result<()> {
+    loop {
+        let sym = lit.decode(r)? as usize;
+
+        if sym < 256 {
+            out.push(sym as u8);
+        } else if sym == 256 {
+            return Ok(());
+        } else {
+            let sym = sym - 257;
+            if sym >= LENGTH_BASE.len() {
+                return Err(invalid("bad length code in deflate stream"));
+            }
+            let len = LENGTH_BASE[sym] as usize + r.bits(LENGTH_EXTRA[sym] as u32)? as usize;
+
+            let d = dist.decode(r)? as usize;
+            if d >= DIST_BASE.len() {
+                return Err(invalid("bad distance code in deflate stream"));
+            }
+            let d = DIST_BASE[d] as usize + r.bits(DIST_EXTRA[d] as u32)? as usize;
+
+            if d > out.len() {
+                return Err(invalid("deflate distance too far back"));
+            }
+
+            let start = out.len() - d;
+            for i in 0..len {
+                let b = out[start + i];
+                out.push(b);
+            }
+        }
+    }
+}
+
+/// decompress a raw deflate stream
+fn inflate(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut r = BitReader { data, pos: 0, bit: 0 };
+    let mut out = Vec::new();
+
+    loop {
+        let last = r.bits(1)? == 1;
+
+        match r.bits(2)? {
+            0 => {
+                r.align();
+                let header = data.get(r.pos .. r.pos + 4).ok_or_else(|| invalid("truncated deflate stream"))?;
+                let len = header[0] as usize | (header[1] as usize) << 8;
+                let nlen = header[2] as usize | (header[3] as usize) << 8;
+
+                if len != !nlen & 0xFFFF {
+                    return Err(invalid("corrupt stored block length"));
+                }
+
+                let start = r.pos + 4;
+                let block = data.get(start .. start + len).ok_or_else(|| invalid("truncated deflate stream"))?;
+                out.extend_from_slice(block);
+                r.pos = start + len;
+            },
+            1 => {
+                let (lit, dist) = fixed_tables();
+                inflate_block(&mut r, &mut out, &lit, &dist)?;
+            },
+            2 => {
+                let (lit, dist) = dynamic_tables(&mut r)?;
+                inflate_block(&mut r, &mut out, &lit, &dist)?;
+            },
+            _ => return Err(invalid("bad deflate block type")),
+        }
+
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+/// decompress a zlib stream, checking its checksum
+pub(crate) fn zlib_decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    if data.len() < 6 || data[0] & 0x0F != 8 || ((data[0] as u16) << 8 | data[1] as u16) % 31 != 0 {
+        return Err(invalid("bad zlib header"));
+    }
+
+    if data[1] & 0x20 != 0 {
+        return Err(invalid("zlib preset dictionaries aren't supported"));
+    }
+
+    let out = inflate(&data[2..])?;
+
+    let tail = &data[data.len() - 4..];
+    let expected = (tail[0] as u32) << 24 | (tail[1] as u32) << 16 | (tail[2] as u32) << 8 | tail[3] as u32;
+    if adler32(&out) != expected {
+        return Err(invalid("zlib checksum mismatch"));
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::zlib_decompress;
+    use codec::deflate::zlib_compress;
+
+    #[test]
+    fn roundtrip() {
+        let inputs: Vec<Vec<u8>> = vec![
+            vec![],
+            b"hello hello hello hello".to_vec(),
+            (0..70000u32).map(|i| (i * 31 % 251) as u8).collect(),
+            (0..70000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8).collect(),
+        ];
+
+        for data in inputs {
+            assert_eq!(zlib_decompress(&zlib_compress(&data)).unwrap(), data);
+        }
+    }
+}
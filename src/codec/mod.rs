@@ -0,0 +1,16 @@
+//! self contained image file formats
+
+use std::io;
+
+pub(crate) mod deflate;
+pub(crate) mod inflate;
+pub(crate) mod png;
+pub(crate) mod pnm;
+pub(crate) mod bmp;
+pub(crate) mod gif;
+pub(crate) mod y4m;
+
+/// error for malformed image files
+pub(crate) fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
@@ -0,0 +1,397 @@
+//! png encoding and decoding
+
+use std::io::{self, Read, Write};
+
+use image::ImageData;
+use super::deflate::{self, Crc32};
+use super::inflate;
+use super::invalid;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+const COLOR_GREY: u8 = 0;
+const COLOR_RGB: u8 = 2;
+const COLOR_PALETTE: u8 = 3;
+const COLOR_GREY_ALPHA: u8 = 4;
+const COLOR_RGBA: u8 = 6;
+
+/// starting offset and spacing of each adam7 pass
+const ADAM7: [(u32, u32, u32, u32); 7] = [
+    (0, 0, 8, 8), (4, 0, 8, 8), (0, 4, 4, 8), (2, 0, 4, 4),
+    (0, 2, 2, 4), (1, 0, 2, 2), (0, 1, 1, 2),
+];
+
+fn write_chunk<W: Write>(w: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    let len = data.len() as u32;
+    w.write_all(&[(len >> 24) as u8, (len >> 16) as u8, (len >> 8) as u8, len as u8])?;
+    w.write_all(kind)?;
+    w.write_all(data)?;
+
+    let mut crc = Crc32::new();
+    crc.update(kind);
+    crc.update(data);
+
+    let crc = crc.finish();
+    w.write_all(&[(crc >> 24) as u8, (crc >> 16) as u8, (crc >> 8) as u8, crc as u8])
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// apply png filter `kind` to a scanline, given the unfiltered previous one
+fn filter_row(kind: u8, bpp: usize, row: &[u8], prev: &[u8], out: &mut Vec<u8>) {
+    out.push(kind);
+
+    for i in 0..row.len() {
+        let a = if i >= bpp { row[i - bpp] } else { 0 };
+        let b = prev[i];
+        let c = if i >= bpp { prev[i - bpp] } else { 0 };
+
+        let predicted = match kind {
+            0 => 0,
+            1 => a,
+            2 => b,
+            3 => ((a as u16 + b as u16) / 2) as u8,
+            _ => paeth(a, b, c),
+        };
+
+        out.push(row[i].wrapping_sub(predicted));
+    }
+}
+
+/// pick the filter with the smallest sum of absolute differences for each
+/// row, which is the heuristic libpng recommends
+fn filter_image(raw: &[u8], rows: usize, stride: usize, bpp: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(raw.len() + rows);
+    let mut scratch = Vec::with_capacity(stride + 1);
+    let zero = vec![0; stride];
+
+    for y in 0..rows {
+        let row = &raw[y * stride .. (y + 1) * stride];
+        let prev = if y > 0 { &raw[(y - 1) * stride .. y * stride] } else { &zero[..] };
+
+        let mut best = (u64::max_value(), 0);
+        for kind in 0..5 {
+            scratch.clear();
+            filter_row(kind, bpp, row, prev, &mut scratch);
+
+            let cost = scratch[1..].iter().map(|&b| (b as i8 as i16).abs() as u64).sum();
+            if cost < best.0 {
+                best = (cost, kind);
+            }
+        }
+
+        filter_row(best.1, bpp, row, prev, &mut out);
+    }
+
+    out
+}
+
+/// encode an image as an 8 bit png
+///
+/// the alpha channel is only written if the image has any pixels that
+/// aren't fully opaque.
+pub(crate) fn encode<W: Write>(img: &ImageData, mut w: W) -> io::Result<()> {
+    let alpha = img.pixels.iter().any(|p| p[3] != 0xFF);
+    let (color_type, bpp) = if alpha { (COLOR_RGBA, 4) } else { (COLOR_RGB, 3) };
+
+    let mut raw = Vec::with_capacity(img.pixels.len() * bpp);
+    for p in &img.pixels {
+        raw.extend(&p[..bpp]);
+    }
+
+    let (width, height) = (img.width, img.height);
+    let ihdr = [
+        (width >> 24) as u8, (width >> 16) as u8, (width >> 8) as u8, width as u8,
+        (height >> 24) as u8, (height >> 16) as u8, (height >> 8) as u8, height as u8,
+        8, // bit depth
+        color_type,
+        0, // deflate
+        0, // adaptive filtering
+        0, // no interlacing
+    ];
+
+    let filtered = filter_image(&raw, height as usize, width as usize * bpp, bpp);
+    let idat = deflate::zlib_compress(&filtered);
+
+    w.write_all(&SIGNATURE)?;
+    write_chunk(&mut w, b"IHDR", &ihdr)?;
+    write_chunk(&mut w, b"IDAT", &idat)?;
+    write_chunk(&mut w, b"IEND", &[])?;
+
+    w.flush()
+}
+
+fn be_u32(b: &[u8]) -> u32 {
+    (b[0] as u32) << 24 | (b[1] as u32) << 16 | (b[2] as u32) << 8 | b[3] as u32
+}
+
+/// undo png filter `kind` in place, given the unfiltered previous scanline
+fn unfilter_row(kind: u8, bpp: usize, row: &mut [u8], prev: &[u8]) -> io::Result<()> {
+    for i in 0..row.len() {
+        let a = if i >= bpp { row[i - bpp] } else { 0 };
+        let b = prev[i];
+        let c = if i >= bpp { prev[i - bpp] } else { 0 };
+
+        let predicted = match kind {
+            0 => 0,
+            1 => a,
+            2 => b,
+            3 => ((a as u16 + b as u16) / 2) as u8,
+            4 => paeth(a, b, c),
+            _ => return Err(invalid("unknown png filter type")),
+        };
+
+        row[i] = row[i].wrapping_add(predicted);
+    }
+
+    Ok(())
+}
+
+/// everything needed to turn raw scanlines into rgba
+struct Format {
+    color_type: u8,
+    depth: u8,
+    palette: Vec<[u8; 4]>,
+    /// transparent color key for grey and rgb images, at full bit depth
+    key: Option<[u16; 3]>,
+}
+
+impl Format {
+    fn channels(&self) -> usize {
+        match self.color_type {
+            COLOR_GREY | COLOR_PALETTE => 1,
+            COLOR_GREY_ALPHA => 2,
+            COLOR_RGB => 3,
+            _ => 4,
+        }
+    }
+
+    fn bits_per_pixel(&self) -> usize {
+        self.channels() * self.depth as usize
+    }
+
+    /// sample `n` of pixel `x` in a scanline, at full bit depth
+    fn sample(&self, row: &[u8], x: usize, n: usize) -> u16 {
+        let index = x * self.channels() + n;
+
+        match self.depth {
+            16 => (row[index * 2] as u16) << 8 | row[index * 2 + 1] as u16,
+            8 => row[index] as u16,
+            d => {
+                let bit = index * d as usize;
+                ((row[bit / 8] >> (8 - d as usize - bit % 8)) & ((1 << d) - 1)) as u16
+            },
+        }
+    }
+
+    /// scale a sample down to 8 bits
+    fn scale(&self, v: u16) -> u8 {
+        match self.depth {
+            16 => (v >> 8) as u8,
+            d => (v as u32 * 255 / ((1 << d) - 1)) as u8,
+        }
+    }
+
+    fn pixel(&self, row: &[u8], x: usize) -> [u8; 4] {
+        match self.color_type {
+            COLOR_PALETTE => {
+                let i = self.sample(row, x, 0) as usize;
+                self.palette.get(i).cloned().unwrap_or([0, 0, 0, 0xFF])
+            },
+            COLOR_GREY => {
+                let v = self.sample(row, x, 0);
+                let a = if self.key.map(|k| k[0]) == Some(v) { 0 } else { 0xFF };
+                let v = self.scale(v);
+                [v, v, v, a]
+            },
+            COLOR_GREY_ALPHA => {
+                let v = self.scale(self.sample(row, x, 0));
+                [v, v, v, self.scale(self.sample(row, x, 1))]
+            },
+            COLOR_RGB => {
+                let c = [self.sample(row, x, 0), self.sample(row, x, 1), self.sample(row, x, 2)];
+                let a = if self.key == Some(c) { 0 } else { 0xFF };
+                [self.scale(c[0]), self.scale(c[1]), self.scale(c[2]), a]
+            },
+            _ => [
+                self.scale(self.sample(row, x, 0)), self.scale(self.sample(row, x, 1)),
+                self.scale(self.sample(row, x, 2)), self.scale(self.sample(row, x, 3)),
+            ],
+        }
+    }
+}
+
+/// the size and bytes per row of an interlace pass, unless it's empty
+fn pass_size(width: u32, height: u32, pass: (u32, u32, u32, u32), bits: usize) -> Option<(u32, u32, u64)> {
+    let (x0, y0, dx, dy) = pass;
+    if x0 >= width || y0 >= height {
+        return None;
+    }
+
+    let pass_width = (width - x0 + dx - 1) / dx;
+    let pass_height = (height - y0 + dy - 1) / dy;
+    Some((pass_width, pass_height, (pass_width as u64 * bits as u64 + 7) / 8))
+}
+
+/// decode a png of any color type and bit depth, interlaced or not
+pub(crate) fn decode<R: Read>(mut r: R) -> io::Result<ImageData> {
+    let mut data = Vec::new();
+    r.read_to_end(&mut data)?;
+
+    if !data.starts_with(&SIGNATURE) {
+        return Err(invalid("not a png file"));
+    }
+
+    let mut header = None;
+    let mut palette = Vec::new();
+    let mut trns = None;
+    let mut idat = Vec::new();
+
+    let mut pos = SIGNATURE.len();
+    loop {
+        let chunk = data.get(pos .. pos + 8).ok_or_else(|| invalid("truncated png chunk"))?;
+        let len = be_u32(chunk) as usize;
+        let kind = [chunk[4], chunk[5], chunk[6], chunk[7]];
+
+        let body = data.get(pos + 8 .. pos + 8 + len).ok_or_else(|| invalid("truncated png chunk"))?;
+        let crc = data.get(pos + 8 + len .. pos + 12 + len).ok_or_else(|| invalid("truncated png chunk"))?;
+
+        let mut check = Crc32::new();
+        check.update(&kind);
+        check.update(body);
+        if check.finish() != be_u32(crc) {
+            return Err(invalid("png chunk checksum mismatch"));
+        }
+
+        match &kind {
+            b"IHDR" if len == 13 => header = Some(body.to_vec()),
+            b"PLTE" => palette = body.chunks(3).filter(|c| c.len() == 3).map(|c| [c[0], c[1], c[2], 0xFF]).collect(),
+            b"tRNS" => trns = Some(body.to_vec()),
+            b"IDAT" => idat.extend_from_slice(body),
+            b"IEND" => break,
+            _ => (),
+        }
+
+        pos += 12 + len;
+    }
+
+    let header = header.ok_or_else(|| invalid("png is missing its header"))?;
+    let (width, height) = (be_u32(&header[0..4]), be_u32(&header[4..8]));
+    let (depth, color_type, interlace) = (header[8], header[9], header[12]);
+
+    let valid_depth = match color_type {
+        COLOR_GREY => [1, 2, 4, 8, 16].contains(&depth),
+        COLOR_PALETTE => [1, 2, 4, 8].contains(&depth),
+        COLOR_RGB | COLOR_GREY_ALPHA | COLOR_RGBA => depth == 8 || depth == 16,
+        _ => false,
+    };
+
+    if !valid_depth || header[10] != 0 || header[11] != 0 || interlace > 1 {
+        return Err(invalid("unsupported png format"));
+    }
+
+    let mut key = None;
+    if let Some(trns) = trns {
+        let be_u16 = |i: usize| trns.get(i .. i + 2).map(|b| (b[0] as u16) << 8 | b[1] as u16);
+
+        match color_type {
+            COLOR_PALETTE => for (p, &a) in palette.iter_mut().zip(&trns) {
+                p[3] = a;
+            },
+            COLOR_GREY => key = be_u16(0).map(|v| [v, 0, 0]),
+            COLOR_RGB => if let (Some(r), Some(g), Some(b)) = (be_u16(0), be_u16(2), be_u16(4)) {
+                key = Some([r, g, b]);
+            },
+            _ => (),
+        }
+    }
+
+    if width == 0 || height == 0 || (width as usize).checked_mul(height as usize).is_none() {
+        return Err(invalid("png image size out of range"));
+    }
+
+    let format = Format { color_type, depth, palette, key };
+    let raw = inflate::zlib_decompress(&idat)?;
+
+    let bits = format.bits_per_pixel();
+    let bpp = (bits / 8).max(1);
+
+    // check the header against the data before trusting it with an allocation
+    let passes: &[(u32, u32, u32, u32)] = if interlace == 1 { &ADAM7 } else { &[(0, 0, 1, 1)] };
+    let expected: u64 = passes.iter()
+        .filter_map(|&pass| pass_size(width, height, pass, bits))
+        .map(|(_, pass_height, stride)| pass_height as u64 * (1 + stride))
+        .sum();
+
+    if expected > raw.len() as u64 {
+        return Err(invalid("truncated png image data"));
+    }
+
+    let mut img = ImageData::new(width, height);
+    let mut pos = 0;
+
+    for &(x0, y0, dx, dy) in passes {
+        let (pass_width, pass_height, stride) = match pass_size(width, height, (x0, y0, dx, dy), bits) {
+            Some((w, h, stride)) => (w as usize, h, stride as usize),
+            None => continue,
+        };
+        let mut prev = vec![0; stride];
+
+        for j in 0..pass_height {
+            let line = raw.get(pos .. pos + 1 + stride).ok_or_else(|| invalid("truncated png image data"))?;
+            let mut row = line[1..].to_vec();
+            unfilter_row(line[0], bpp, &mut row, &prev)?;
+
+            for i in 0..pass_width {
+                let p = format.pixel(&row, i);
+                img.set_pixel(x0 + i as u32 * dx, y0 + j * dy, p);
+            }
+
+            prev = row;
+            pos += 1 + stride;
+        }
+    }
+
+    Ok(img)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, write_chunk, SIGNATURE};
+    use codec::deflate::zlib_compress;
+
+    fn forged(width: u32, height: u32, raw: &[u8]) -> Vec<u8> {
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&[(width >> 24) as u8, (width >> 16) as u8, (width >> 8) as u8, width as u8]);
+        ihdr.extend_from_slice(&[(height >> 24) as u8, (height >> 16) as u8, (height >> 8) as u8, height as u8]);
+        ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+        let mut png = SIGNATURE.to_vec();
+        write_chunk(&mut png, b"IHDR", &ihdr).unwrap();
+        write_chunk(&mut png, b"IDAT", &zlib_compress(raw)).unwrap();
+        write_chunk(&mut png, b"IEND", &[]).unwrap();
+        png
+    }
+
+    #[test]
+    fn forged_sizes() {
+        assert_eq!(decode(&forged(1, 1, &[0, 1, 2, 3])[..]).unwrap().pixels, vec![[1, 2, 3, 0xFF]]);
+
+        // a huge header with nothing behind it is rejected before allocating
+        assert!(decode(&forged(70000, 70000, &[])[..]).is_err());
+        assert!(decode(&forged(0x7FFF_FFFF, 1, &[0; 64])[..]).is_err());
+        assert!(decode(&forged(2, 1, &[0, 1, 2, 3])[..]).is_err());
+        assert!(decode(&forged(0, 5, &[])[..]).is_err());
+    }
+}
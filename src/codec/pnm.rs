@@ -0,0 +1,233 @@
+//! netpbm formats: pbm, pgm, ppm and pam
+
+use std::io::{self, Read, Write};
+
+use image::ImageData;
+use super::invalid;
+
+/// rec. 601 luma, rounded
+pub(crate) fn luma(p: [u8; 4]) -> u8 {
+    ((p[0] as u32 * 77 + p[1] as u32 * 150 + p[2] as u32 * 29 + 128) >> 8) as u8
+}
+
+/// write a binary ppm, dropping the alpha channel
+pub(crate) fn encode_ppm<W: Write>(img: &ImageData, mut w: W) -> io::Result<()> {
+    write!(w, "P6\n{} {}\n255\n", img.width, img.height)?;
+
+    let mut data = Vec::with_capacity(img.pixels.len() * 3);
+    for p in &img.pixels {
+        data.extend(&p[..3]);
+    }
+
+    w.write_all(&data)?;
+    w.flush()
+}
+
+/// write a binary pgm, converting the image to greyscale
+pub(crate) fn encode_pgm<W: Write>(img: &ImageData, mut w: W) -> io::Result<()> {
+    write!(w, "P5\n{} {}\n255\n", img.width, img.height)?;
+
+    let data: Vec<u8> = img.pixels.iter().map(|&p| luma(p)).collect();
+
+    w.write_all(&data)?;
+    w.flush()
+}
+
+/// write a pam with all four channels
+pub(crate) fn encode_pam<W: Write>(img: &ImageData, mut w: W) -> io::Result<()> {
+    write!(w, "P7\nWIDTH {}\nHEIGHT {}\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n",
+        img.width, img.height)?;
+
+    let mut data = Vec::with_capacity(img.pixels.len() * 4);
+    for p in &img.pixels {
+        data.extend(p);
+    }
+
+    w.write_all(&data)?;
+    w.flush()
+}
+
+/// a cursor over the header of a netpbm file
+struct Header<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Header<'a> {
+    fn skip_space(&mut self) {
+        while self.pos < self.data.len() {
+            match self.data[self.pos] {
+                b'#' => {
+                    while self.pos < self.data.len() && self.data[self.pos] != b'\n' {
+                        self.pos += 1;
+                    }
+                },
+                b' ' | b'\t' | b'\r' | b'\n' => self.pos += 1,
+                _ => break,
+            }
+        }
+    }
+
+    fn token(&mut self) -> io::Result<&'a str> {
+        self.skip_space();
+
+        let start = self.pos;
+        while self.pos < self.data.len() && !(self.data[self.pos] as char).is_whitespace() {
+            self.pos += 1;
+        }
+
+        if start == self.pos {
+            return Err(invalid("unexpected end of netpbm header"));
+        }
+
+        ::std::str::from_utf8(&self.data[start .. self.pos])
+            .map_err(|_| invalid("netpbm header isn't ascii"))
+    }
+
+    fn number(&mut self) -> io::Result<u32> {
+        self.token()?.parse().map_err(|_| invalid("bad number in netpbm header"))
+    }
+
+    /// a single ascii pbm digit, which doesn't need whitespace in between
+    fn bit(&mut self) -> io::Result<u32> {
+        self.skip_space();
+
+        match self.data.get(self.pos) {
+            Some(&b'0') => { self.pos += 1; Ok(0) },
+            Some(&b'1') => { self.pos += 1; Ok(1) },
+            _ => Err(invalid("bad pbm pixel data")),
+        }
+    }
+}
+
+/// decode any netpbm file, from `P1` to `P7`
+pub(crate) fn decode<R: Read>(mut r: R) -> io::Result<ImageData> {
+    let mut data = Vec::new();
+    r.read_to_end(&mut data)?;
+
+    let mut h = Header { data: &data, pos: 0 };
+    let magic = h.token()?;
+
+    let (width, height, depth, maxval) = match magic {
+        "P1" | "P4" => {
+            let (w, ht) = (h.number()?, h.number()?);
+            (w, ht, 1, 1)
+        },
+        "P2" | "P5" => {
+            let (w, ht, m) = (h.number()?, h.number()?, h.number()?);
+            (w, ht, 1, m)
+        },
+        "P3" | "P6" => {
+            let (w, ht, m) = (h.number()?, h.number()?, h.number()?);
+            (w, ht, 3, m)
+        },
+        "P7" => {
+            let (mut w, mut ht, mut depth, mut maxval) = (None, None, None, None);
+            loop {
+                match h.token()? {
+                    "WIDTH" => w = Some(h.number()?),
+                    "HEIGHT" => ht = Some(h.number()?),
+                    "DEPTH" => depth = Some(h.number()?),
+                    "MAXVAL" => maxval = Some(h.number()?),
+                    "TUPLTYPE" => { h.token()?; },
+                    "ENDHDR" => break,
+                    _ => return Err(invalid("unknown pam header field")),
+                }
+            }
+
+            match (w, ht, depth, maxval) {
+                (Some(w), Some(ht), Some(d), Some(m)) if d >= 1 && d <= 4 => (w, ht, d, m),
+                _ => return Err(invalid("incomplete or unsupported pam header")),
+            }
+        },
+        _ => return Err(invalid("not a netpbm file")),
+    };
+
+    if maxval == 0 || maxval > 65535 {
+        return Err(invalid("netpbm maxval out of range"));
+    }
+
+    let too_large = || invalid("netpbm image too large");
+    let count = (width as usize).checked_mul(height as usize).ok_or_else(too_large)?;
+    let mut samples = Vec::new();
+
+    match magic {
+        "P1" => {
+            for _ in 0 .. count {
+                samples.push(h.bit()?);
+            }
+        },
+        "P2" | "P3" => {
+            for _ in 0 .. count.checked_mul(depth as usize).ok_or_else(too_large)? {
+                samples.push(h.number()?);
+            }
+        },
+        _ => {
+            // exactly one whitespace byte separates the header from binary data
+            let start = h.pos + 1;
+            let body = data.get(start..).unwrap_or(&[]);
+
+            if magic == "P4" {
+                let stride = (width as usize + 7) / 8;
+                if body.len() < stride.checked_mul(height as usize).ok_or_else(too_large)? {
+                    return Err(invalid("truncated pbm data"));
+                }
+
+                for y in 0 .. height as usize {
+                    for x in 0 .. width as usize {
+                        samples.push(((body[y * stride + x / 8] >> (7 - x % 8)) & 1) as u32);
+                    }
+                }
+            } else {
+                let bytes = if maxval > 255 { 2 } else { 1 };
+                let len = count.checked_mul(depth as usize * bytes).ok_or_else(too_large)?;
+                if body.len() < len {
+                    return Err(invalid("truncated netpbm data"));
+                }
+
+                for s in body[..len].chunks(bytes) {
+                    samples.push(if bytes == 2 {
+                        (s[0] as u32) << 8 | s[1] as u32
+                    } else {
+                        s[0] as u32
+                    });
+                }
+            }
+        },
+    }
+
+    let scale = |s: u32| -> u8 {
+        if maxval == 255 {
+            s.min(255) as u8
+        } else {
+            ((s.min(maxval) * 255 + maxval / 2) / maxval) as u8
+        }
+    };
+
+    let pixels = samples.chunks(depth as usize).map(|s| {
+        match (magic, depth) {
+            // in pbm files 1 is black
+            ("P1", _) | ("P4", _) => if s[0] == 1 { [0, 0, 0, 0xFF] } else { [0xFF, 0xFF, 0xFF, 0xFF] },
+            (_, 1) => { let v = scale(s[0]); [v, v, v, 0xFF] },
+            (_, 2) => { let v = scale(s[0]); [v, v, v, scale(s[1])] },
+            (_, 3) => [scale(s[0]), scale(s[1]), scale(s[2]), 0xFF],
+            _ => [scale(s[0]), scale(s[1]), scale(s[2]), scale(s[3])],
+        }
+    }).collect();
+
+    Ok(ImageData::from_pixels(width, height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode;
+
+    #[test]
+    fn forged_sizes() {
+        for header in &[&b"P6 4294967295 4294967295 255\n"[..], b"P3 4294967295 4294967295 255\n",
+                        b"P7 WIDTH 4294967295 HEIGHT 4294967295 DEPTH 4 MAXVAL 65535 ENDHDR\n"] {
+            let err = decode(*header).unwrap_err();
+            assert_eq!(err.kind(), ::std::io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! yuv4mpeg2 raw video
+//!
+//! frames are full range bt.601 yuv with 4:2:0 chroma, marked `XCOLORRANGE=FULL`
+
+use std::io::{self, Write};
+
+use image::ImageData;
+
+/// write the stream header for `width` x `height` frames at `fps`
+pub(crate) fn write_header<W: Write>(w: &mut W, width: u32, height: u32, fps: u32) -> io::Result<()> {
+    writeln!(w, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C420jpeg XCOLORRANGE=FULL", width, height, fps)
+}
+
+fn luma(p: [u8; 4]) -> u8 {
+    let (r, g, b) = (p[0] as i32, p[1] as i32, p[2] as i32);
+    ((19595 * r + 38470 * g + 7471 * b + 0x8000) >> 16) as u8
+}
+
+fn chroma(r: i32, g: i32, b: i32) -> (u8, u8) {
+    let clamp = |v: i32| (v >> 16).max(0).min(255) as u8;
+
+    let u = -11059 * r - 21709 * g + 32768 * b;
+    let v = 32768 * r - 27439 * g - 5329 * b;
+
+    (clamp(u + (128 << 16) + 0x8000), clamp(v + (128 << 16) + 0x8000))
+}
+
+/// write one frame, ignoring alpha and averaging chroma over 2x2 blocks
+pub(crate) fn write_frame<W: Write>(w: &mut W, img: &ImageData) -> io::Result<()> {
+    let (width, height) = (img.width as usize, img.height as usize);
+    let (cw, ch) = ((width + 1) / 2, (height + 1) / 2);
+
+    let mut data = Vec::with_capacity(width * height + 2 * cw * ch);
+    data.extend(img.pixels.iter().map(|&p| luma(p)));
+
+    let mut u = Vec::with_capacity(cw * ch);
+    let mut v = Vec::with_capacity(cw * ch);
+
+    for cy in 0..ch {
+        for cx in 0..cw {
+            let (mut r, mut g, mut b, mut n) = (0, 0, 0, 0);
+
+            for y in cy * 2 .. (cy * 2 + 2).min(height) {
+                for x in cx * 2 .. (cx * 2 + 2).min(width) {
+                    let p = img.pixels[y * width + x];
+                    r += p[0] as i32;
+                    g += p[1] as i32;
+                    b += p[2] as i32;
+                    n += 1;
+                }
+            }
+
+            let (cb, cr) = chroma((r + n / 2) / n, (g + n / 2) / n, (b + n / 2) / n);
+            u.push(cb);
+            v.push(cr);
+        }
+    }
+
+    data.extend(u);
+    data.extend(v);
+
+    w.write_all(b"FRAME\n")?;
+    w.write_all(&data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_frame, write_header};
+    use image::ImageData;
+
+    #[test]
+    fn frame_layout() {
+        let mut img = ImageData::from_pixels(3, 1, vec![[0xFF, 0xFF, 0xFF, 0xFF]; 3]);
+        img.set_pixel(2, 0, [0xFF, 0, 0, 0xFF]);
+
+        let mut out = Vec::new();
+        write_header(&mut out, 3, 1, 25).unwrap();
+        write_frame(&mut out, &img).unwrap();
+
+        let header = b"YUV4MPEG2 W3 H1 F25:1 Ip A1:1 C420jpeg XCOLORRANGE=FULL\nFRAME\n";
+        assert_eq!(&out[..header.len()], &header[..]);
+
+        // three luma samples, then two samples for each chroma plane
+        assert_eq!(&out[header.len()..], &[255, 255, 76, 128, 85, 128, 255][..]);
+    }
+}
@@ -81,6 +81,19 @@ impl Add for Rgb {
     }
 }
 
+impl Rgba {
+    /// 8 bit color with the alpha scaled by `coverage`, for blending
+    pub(crate) fn with_coverage(self, coverage: f32) -> [u8; 4] {
+        let c = (self.0).clamp();
+        let a = (self.1 * coverage).max(0.0).min(1.0);
+
+        [(c.0 * 255.0).round() as u8,
+         (c.1 * 255.0).round() as u8,
+         (c.2 * 255.0).round() as u8,
+         (a * 255.0).round() as u8]
+    }
+}
+
 impl Default for Rgb {
     fn default() -> Self {
         Rgb(0.0, 0.0, 0.0)
@@ -99,6 +112,27 @@ impl From<(Rgb, f32)> for Rgba {
     }
 }
 
+/// composite an 8 bit rgba color over another one
+///
+/// this is the usual "source over" operation on non-premultiplied colors
+pub(crate) fn blend(src: [u8; 4], dst: [u8; 4]) -> [u8; 4] {
+    let (sa, da) = (src[3] as u32, dst[3] as u32);
+
+    if sa == 0xFF || da == 0 {
+        return src;
+    } else if sa == 0 {
+        return dst;
+    }
+
+    // alpha of the result, scaled by 255
+    let a = sa * 255 + da * (255 - sa);
+    let mix = |s: u8, d: u8| {
+        ((s as u32 * sa * 255 + d as u32 * da * (255 - sa) + a / 2) / a) as u8
+    };
+
+    [mix(src[0], dst[0]), mix(src[1], dst[1]), mix(src[2], dst[2]), ((a + 127) / 255) as u8]
+}
+
 /// any value that could be used as a color
 /// 
 /// including:
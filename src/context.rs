@@ -1,24 +1,130 @@
 //! a drawing context
 //! each canvas has exactly one
+//!
+//! shapes and images are placed with `f32` coordinates, and calls that
+//! address single pixels take `i32`. either can be partly or entirely off
+//! the canvas on any side, and gets clipped to it.
+//!
+//! shapes and images also go through the current `Transform`, so they can
+//! be drawn in whatever coordinates suit the drawing. pixels are always
+//! addressed on the canvas itself.
+
+use std::borrow::Cow;
+use std::f32::consts::PI;
+use std::io::{self, Write};
+use std::fs::File;
+use std::path::Path as FilePath;
+use std::time::Instant;
 
 use color::{self, CanvasColor};
+use backend::{self, Backend};
+use image::{pixel_count, Filter, ImageData, ImageFormat, ImageSource};
+use path::{self, LineCap, LineJoin, Path, Point, StrokeStyle};
+use raster::{self, Connectivity, Coverage, FillRule};
+use record::{Clock, GifRecorder, VideoRecorder};
+use resample;
+use transform::Transform;
+
+/// what clearing the canvas leaves behind
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Background {
+    /// pixels of one color
+    Solid([u8; 4]),
+    /// transparent black
+    Transparent,
+    /// transparent black, shown over a checkerboard in a window so it can
+    /// be told apart from actual black
+    Checkerboard,
+}
+
+impl Background {
+    /// a solid background in any color the canvas accepts
+    pub fn solid<C: CanvasColor>(color: C) -> Self {
+        let color: color::Rgba = (color.as_rgb(), color.alpha()).into();
+        Background::Solid(color.with_coverage(1.0))
+    }
+
+    fn pixel(self) -> [u8; 4] {
+        match self {
+            Background::Solid(pixel) => pixel,
+            Background::Transparent | Background::Checkerboard => [0; 4],
+        }
+    }
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Checkerboard
+    }
+}
 
 pub struct Context {
     width: u32,
     height: u32,
+    background: Background,
     fill_color: color::Rgba,
-    pub(crate) window: ::pipeline::GlWindow,
+    stroke_color: color::Rgba,
+    stroke_style: StrokeStyle,
+    antialias: bool,
+    fill_rule: FillRule,
+    path: Path,
+    image_filter: Filter,
+    transform: Transform,
+    transforms: Vec<Transform>,
+    backend: Box<Backend>,
+    clock: Clock,
+    recording: Option<GifRecorder>,
+    video: Option<VideoRecorder>,
 }
 
 impl Context {
     pub fn new(width: u32, height: u32, ev_loop: &::glutin::EventsLoop) -> Self {
         let window = ::pipeline::init(width, height, ev_loop);
-        
+
+        Context::with_backend(width, height, window)
+    }
+
+    /// create a context that draws into main memory instead of a window
+    ///
+    /// time is virtual, every call to `present` lasts one frame at 60 fps
+    pub fn headless(width: u32, height: u32) -> Self {
+        let mut ctx = Context::with_backend(width, height, backend::Software::new(width, height));
+        ctx.frame_rate(60);
+        ctx
+    }
+
+    /// create a transparent off-screen canvas, which can be drawn with `draw_image`
+    pub fn create_offscreen(&self, width: u32, height: u32) -> Context {
+        let mut ctx = Context::with_backend(width, height, backend::Software::new(width, height));
+        ctx.background(Background::Transparent);
+        ctx.image_filter = self.image_filter;
+        ctx.frame_rate(60);
+        ctx
+    }
+
+    /// create a context that draws using a custom backend
+    ///
+    /// `width` and `height` should match the current size of the backend.
+    pub fn with_backend<B>(width: u32, height: u32, backend: B) -> Self
+        where B: Backend + 'static
+    {
         Context {
             width,
             height,
+            background: Default::default(),
             fill_color: Default::default(),
-            window,
+            stroke_color: Default::default(),
+            stroke_style: Default::default(),
+            antialias: false,
+            fill_rule: Default::default(),
+            path: Path::new(),
+            image_filter: Default::default(),
+            transform: Transform::identity(),
+            transforms: Vec::new(),
+            backend: Box::new(backend),
+            clock: Clock::Real(Instant::now()),
+            recording: None,
+            video: None,
         }
     }
 
@@ -28,9 +134,110 @@ impl Context {
     }
 
     pub(crate) fn resize(&mut self, w: u32, h: u32) {
-        self.window.resize(w, h);
+        let (old_w, old_h) = (self.width as i64, self.height as i64);
+
+        self.backend.resize(w, h);
         self.width = w;
         self.height = h;
+
+        // new space starts out as background
+        let (w, h, pixel) = (w as i64, h as i64, self.background.pixel());
+        self.paint_rect(old_w, 0, w - old_w, h, pixel);
+        self.paint_rect(0, old_h, old_w.min(w), h - old_h, pixel);
+    }
+
+    /// show the current contents of the canvas, and hand them to any
+    /// recordings in progress
+    pub fn present(&mut self) {
+        self.backend.present();
+
+        // the previous frame lasts until now
+        self.clock.tick();
+        let now = self.clock.now();
+
+        if let Some((w, h)) = self.recording.as_ref().map(GifRecorder::dimensions) {
+            let frame = self.get_image_data(0, 0, w, h);
+            self.recording.as_mut().unwrap().capture(frame, now);
+        }
+
+        if let Some((w, h)) = self.video.as_ref().map(VideoRecorder::dimensions) {
+            let frame = self.get_image_data(0, 0, w, h);
+            self.video.as_mut().unwrap().capture(frame, now);
+        }
+    }
+
+    /// use a fixed frame rate instead of the wall clock, each `present` lasting `1 / fps` seconds
+    pub fn frame_rate(&mut self, fps: u32) {
+        self.clock.set_frame_rate(fps);
+    }
+
+    /// start recording an animated gif of every frame that's presented
+    pub fn start_recording(&mut self) {
+        self.start_recording_at(100);
+    }
+
+    /// start recording an animated gif at no more than `fps` frames per second
+    pub fn start_recording_at(&mut self, fps: u32) {
+        let now = self.clock.now();
+        let mut recorder = GifRecorder::new(self.width, self.height, fps, now);
+        let (w, h) = recorder.dimensions();
+        recorder.capture(self.get_image_data(0, 0, w, h), now);
+
+        self.recording = Some(recorder);
+    }
+
+    /// whether a recording is in progress
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// stop recording, and write the animation to `w`
+    pub fn write_recording<W: Write>(&mut self, mut w: W) -> io::Result<()> {
+        match self.recording.take() {
+            Some(recorder) => w.write_all(&recorder.finish(self.clock.now())),
+            None => Err(io::Error::new(io::ErrorKind::Other, "no recording in progress")),
+        }
+    }
+
+    /// stop recording, and save the animation to a gif file
+    pub fn stop_recording<P: AsRef<FilePath>>(&mut self, path: P) -> io::Result<()> {
+        if !self.is_recording() {
+            return Err(io::Error::new(io::ErrorKind::Other, "no recording in progress"));
+        }
+
+        let file = File::create(path)?;
+        self.write_recording(io::BufWriter::new(file))
+    }
+
+    /// start streaming presented frames to `w` as yuv4mpeg2 video at `fps`
+    ///
+    /// frames are repeated or dropped to keep the rate constant
+    pub fn start_video<W: Write + 'static>(&mut self, w: W, fps: u32) -> io::Result<()> {
+        let now = self.clock.now();
+        let mut recorder = VideoRecorder::new(w, self.width, self.height, fps, now)?;
+        recorder.capture(self.get_image_data(0, 0, self.width, self.height), now);
+
+        self.video = Some(recorder);
+        Ok(())
+    }
+
+    /// start streaming presented frames to a .y4m file
+    pub fn start_video_file<P: AsRef<FilePath>>(&mut self, path: P, fps: u32) -> io::Result<()> {
+        let file = File::create(path)?;
+        self.start_video(io::BufWriter::new(file), fps)
+    }
+
+    /// whether a video is being streamed
+    pub fn is_recording_video(&self) -> bool {
+        self.video.is_some()
+    }
+
+    /// stop streaming video, returning the first error that happened while writing
+    pub fn stop_video(&mut self) -> io::Result<()> {
+        match self.video.take() {
+            Some(recorder) => recorder.finish(self.clock.now()),
+            None => Err(io::Error::new(io::ErrorKind::Other, "no video in progress")),
+        }
     }
 
     /// set fill color
@@ -40,70 +247,1412 @@ impl Context {
         self.fill_color = (color.as_rgb(), color.alpha()).into();
     }
 
+    /// set stroke color
+    pub fn stroke_color<C>(&mut self, color: C)
+        where C: CanvasColor
+    {
+        self.stroke_color = (color.as_rgb(), color.alpha()).into();
+    }
+
+    /// set the width of outlines, in pixels
+    ///
+    /// zero, negative and non-finite widths are ignored.
+    pub fn line_width(&mut self, width: f32) {
+        if width > 0.0 && width.is_finite() {
+            self.stroke_style.width = width;
+        }
+    }
+
+    /// set the lengths of dashes and gaps for all strokes, empty for solid lines
+    ///
+    /// odd numbers of lengths are repeated twice, negative or non-finite ones are ignored.
+    pub fn set_line_dash(&mut self, pattern: &[f32]) {
+        if pattern.iter().any(|&l| l < 0.0 || !l.is_finite()) {
+            return;
+        }
+
+        let mut dash = pattern.to_vec();
+        if dash.len() % 2 == 1 {
+            dash.extend_from_slice(pattern);
+        }
+        self.stroke_style.dash = dash;
+    }
+
+    /// the current dash pattern, always with an even number of lengths
+    pub fn line_dash(&self) -> &[f32] {
+        &self.stroke_style.dash
+    }
+
+    /// set how far into the dash pattern strokes start
+    pub fn line_dash_offset(&mut self, offset: f32) {
+        if offset.is_finite() {
+            self.stroke_style.dash_offset = offset;
+        }
+    }
+
+    /// set how corners of stroked paths are drawn
+    pub fn line_join(&mut self, join: LineJoin) {
+        self.stroke_style.join = join;
+    }
+
+    /// set how the ends of stroked paths are drawn
+    pub fn line_cap(&mut self, cap: LineCap) {
+        self.stroke_style.cap = cap;
+    }
+
+    /// set how far a mitered corner may stick out, in line widths, before it's beveled
+    pub fn miter_limit(&mut self, limit: f32) {
+        if limit > 0.0 && limit.is_finite() {
+            self.stroke_style.miter_limit = limit;
+        }
+    }
+
+    /// smooth the edges of lines and shapes, off by default
+    pub fn antialias(&mut self, enabled: bool) {
+        self.antialias = enabled;
+    }
+
+    /// set the rule `fill` uses where a path crosses itself or its subpaths overlap
+    pub fn fill_rule(&mut self, rule: FillRule) {
+        self.fill_rule = rule;
+    }
+
+    /// set the filter used when drawing images at a different size
+    pub fn image_filter(&mut self, filter: Filter) {
+        self.image_filter = filter;
+    }
+
+    /// move everything drawn from now on by `(x, y)`
+    pub fn translate(&mut self, x: f32, y: f32) {
+        self.transform(Transform::translation(x, y));
+    }
+
+    /// turn everything drawn from now on around the origin, clockwise in radians
+    pub fn rotate(&mut self, angle: f32) {
+        self.transform(Transform::rotation(angle));
+    }
+
+    /// stretch everything drawn from now on away from the origin, by `x`
+    /// horizontally and `y` vertically
+    pub fn scale(&mut self, x: f32, y: f32) {
+        self.transform(Transform::scaling(x, y));
+    }
+
+    /// apply `transform` to everything drawn from now on, before the current
+    /// transform, so the last call applies first like in the browser
+    pub fn transform(&mut self, transform: Transform) {
+        self.transform = transform.then(&self.transform);
+    }
+
+    /// replace the transform that shapes, paths, lines and images are drawn through
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    /// go back to drawing in canvas pixels
+    pub fn reset_transform(&mut self) {
+        self.transform = Transform::identity();
+    }
+
+    /// the current transform
+    pub fn get_transform(&self) -> Transform {
+        self.transform
+    }
+
+    /// remember the current transform, to go back to it with `pop_transform`
+    pub fn push_transform(&mut self) {
+        self.transforms.push(self.transform);
+    }
+
+    /// go back to the transform of the matching `push_transform`, if any
+    pub fn pop_transform(&mut self) {
+        if let Some(transform) = self.transforms.pop() {
+            self.transform = transform;
+        }
+    }
+
+    /// set what `clear`, `clear_rect` and new space from resizing leave behind
+    ///
+    /// defaults to `Background::Checkerboard`, or `Transparent` off-screen.
+    pub fn background(&mut self, background: Background) {
+        self.background = background;
+        self.backend.show_checkerboard(background == Background::Checkerboard);
+    }
+
+    /// reset the pixels whose centers lie inside a rectangle to the background, without blending
+    pub fn clear_rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        let (pixel, t) = (self.background.pixel(), self.transform);
+
+        if t.is_axis_aligned() {
+            let (left, top) = t.apply(x, y);
+            let (right, bottom) = t.apply(x + width, y + height);
+            let (x0, x1) = raster::pixel_range(left, right);
+            let (y0, y1) = raster::pixel_range(top, bottom);
+            self.paint_rect(x0, y0, x1 - x0, y1 - y0, pixel);
+        } else {
+            let mut path = Path::new();
+            path.rect(x, y, width, height);
+            let pixels = self.rasterize(&path.transformed(&t).fill_contours(), FillRule::NonZero, false);
+            self.update_pixels(pixels, |_, _| pixel);
+        }
+    }
+
+    /// reset the whole canvas to the background, whatever the transform
+    pub fn clear(&mut self) {
+        let (width, height, pixel) = (self.width as i64, self.height as i64, self.background.pixel());
+        self.paint_rect(0, 0, width, height, pixel);
+    }
+
     /// fill rectangle with preset fill color
-    pub fn fill_rect(&mut self, x: u32, y: u32, width: u32, height: u32) {
-        if x >= self.width || y >= self.height {
+    ///
+    /// negative sizes extend the rectangle up or to the left.
+    pub fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        let t = self.transform;
+        let (left, top) = t.apply(x, y);
+        let (right, bottom) = t.apply(x + width, y + height);
+        let whole = t.is_axis_aligned() && [left, top, right, bottom].iter().all(|v| v.fract() == 0.0);
+
+        if whole && self.fill_color.alpha() >= 1.0 {
+            // opaque and covering whole pixels, so there's nothing to blend
+            let color = self.fill_color.into_gpu(None);
+            let (x0, x1) = raster::pixel_range(left, right);
+            let (y0, y1) = raster::pixel_range(top, bottom);
+            self.paint_rect(x0, y0, x1 - x0, y1 - y0, color);
+        } else {
+            let mut path = Path::new();
+            path.rect(x, y, width, height);
+            let color = self.fill_color;
+            self.fill_contours(&path.transformed(&t).fill_contours(), FillRule::NonZero, color);
+        }
+    }
+
+    /// draw the outline of a rectangle with the stroke color
+    ///
+    /// the border is centered on the edge of the area `fill_rect` would cover,
+    /// odd widths are moved half a pixel in to cover whole pixels
+    pub fn stroke_rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        let inset = if self.stroke_style.width.round() as i64 % 2 == 1 { 0.5 } else { 0.0 };
+        let (x, width) = if width < 0.0 { (x + width, -width) } else { (x, width) };
+        let (y, height) = if height < 0.0 { (y + height, -height) } else { (y, height) };
+
+        let mut path = Path::new();
+        path.rect(x + inset, y + inset, width - 2.0 * inset, height - 2.0 * inset);
+        self.stroke_path(&path);
+    }
+
+    /// draw a one pixel wide line with the stroke color
+    ///
+    /// both end points are included. with antialiasing on, the line is drawn
+    /// with xiaolin wu's algorithm, otherwise with bresenham's. the line
+    /// dash applies, but the line width doesn't, and the line stays one
+    /// pixel wide under any transform. aliased dashes leave out their last
+    /// pixel, so gaps don't disappear.
+    pub fn draw_line(&mut self, x0: f32, y0: f32, x1: f32, y1: f32) {
+        let (style, t) = (&self.stroke_style, self.transform);
+        let (a, b) = (t.apply(x0, y0), t.apply(x1, y1));
+
+        // only the part near the canvas gets rasterized. the margin keeps
+        // the clipped ends out of sight.
+        let (w, h) = (self.width as f32, self.height as f32);
+        let (clipped, inverse) = match (raster::clip_line(a, b, (-2.0, -2.0), (w + 2.0, h + 2.0)), t.invert()) {
+            (Some(clipped), Some(inverse)) => (clipped, inverse),
+            _ => return,
+        };
+
+        let start = if clipped[0] == a { (x0, y0) } else { inverse.apply(clipped[0].0, clipped[0].1) };
+        let end = if clipped[1] == b { (x1, y1) } else { inverse.apply(clipped[1].0, clipped[1].1) };
+
+        // the dashes carry on from the part that was clipped off
+        let total: f32 = style.dash.iter().sum();
+        let skipped = (start.0 as f64 - x0 as f64).hypot(start.1 as f64 - y0 as f64);
+        let offset = if total > 0.0 {
+            ((style.dash_offset as f64 + skipped) % total as f64) as f32
+        } else {
+            0.0
+        };
+
+        let pieces = path::dashes(&[start, end], false, &style.dash, offset);
+
+        let mut pixels = Vec::new();
+        for piece in pieces {
+            let (a, b) = (piece[0], piece[piece.len() - 1]);
+            let last = b == (x1, y1);
+            let (a, b) = (t.apply(a.0, a.1), t.apply(b.0, b.1));
+
+            if self.antialias {
+                pixels.extend(raster::wu(a.0, a.1, b.0, b.1));
+            } else {
+                let mut dash = raster::bresenham(a.0, a.1, b.0, b.1);
+                if !last {
+                    dash.pop();
+                }
+                pixels.extend(dash);
+            }
+        }
+
+        let color = self.stroke_color;
+        self.paint(pixels, color);
+    }
+
+    /// fill a circle with the fill color
+    pub fn fill_circle(&mut self, cx: f32, cy: f32, radius: f32) {
+        self.fill_ellipse(cx, cy, radius, radius);
+    }
+
+    /// draw the outline of a circle with the stroke color and line width
+    pub fn stroke_circle(&mut self, cx: f32, cy: f32, radius: f32) {
+        self.stroke_ellipse(cx, cy, radius, radius);
+    }
+
+    /// fill an axis aligned ellipse with the fill color
+    pub fn fill_ellipse(&mut self, cx: f32, cy: f32, rx: f32, ry: f32) {
+        let mut path = Path::new();
+        path.ellipse(cx, cy, rx, ry, 0.0, 2.0 * PI, false);
+        self.fill_path(&path);
+    }
+
+    /// draw the outline of an axis aligned ellipse, centered on its edge
+    pub fn stroke_ellipse(&mut self, cx: f32, cy: f32, rx: f32, ry: f32) {
+        let mut path = Path::new();
+        path.ellipse(cx, cy, rx, ry, 0.0, 2.0 * PI, false);
+        path.close_path();
+        self.stroke_path(&path);
+    }
+
+    /// fill a pie slice of a circle, with angles in radians clockwise from the x axis
+    pub fn fill_arc(&mut self, cx: f32, cy: f32, radius: f32, start: f32, end: f32) {
+        let mut path = Path::new();
+        path.move_to(cx, cy);
+        path.arc(cx, cy, radius, start.min(end), start.max(end), false);
+        self.fill_path(&path);
+    }
+
+    /// draw part of the outline of a circle, with the line cap at its ends
+    pub fn stroke_arc(&mut self, cx: f32, cy: f32, radius: f32, start: f32, end: f32) {
+        let mut path = Path::new();
+        path.arc(cx, cy, radius, start.min(end), start.max(end), false);
+        self.stroke_path(&path);
+    }
+
+    /// fill the area around `(x, y)` whose channels are all within `tolerance`
+    /// of that pixel's, like a paint bucket
+    pub fn flood_fill<C>(&mut self, x: i32, y: i32, color: C, tolerance: u8)
+        where C: CanvasColor
+    {
+        self.flood_fill_with(x, y, color, tolerance, Connectivity::Four);
+    }
+
+    /// fill the area around `(x, y)` with a choice of which neighbours count
+    /// as connected
+    pub fn flood_fill_with<C>(&mut self, x: i32, y: i32, color: C, tolerance: u8, connectivity: Connectivity)
+        where C: CanvasColor
+    {
+        let (x, y) = match self.on_canvas(x, y) {
+            Some(p) => p,
+            None => return,
+        };
+
+        let pixels = {
+            let data = self.backend.read_back(0, 0, self.width, self.height);
+            let seed = data[y as usize * self.width as usize + x as usize];
+            let similar = |i: usize| {
+                data[i].iter().zip(&seed).all(|(&a, &b)| (a as i32 - b as i32).abs() <= tolerance as i32)
+            };
+
+            raster::flood(self.width, self.height, x, y, connectivity, similar)
+        };
+
+        self.paint(pixels, (color.as_rgb(), color.alpha()).into());
+    }
+
+    /// fill a rectangle with rounded corners, see `Path::round_rect` for `radii`
+    ///
+    /// like paths, the edges can be anywhere and follow the antialias flag.
+    pub fn fill_round_rect(&mut self, x: f32, y: f32, width: f32, height: f32, radii: &[f32]) {
+        let mut path = Path::new();
+        path.round_rect(x, y, width, height, radii);
+        self.fill_path(&path);
+    }
+
+    /// draw the outline of a rectangle with rounded corners
+    pub fn stroke_round_rect(&mut self, x: f32, y: f32, width: f32, height: f32, radii: &[f32]) {
+        let mut path = Path::new();
+        path.round_rect(x, y, width, height, radii);
+        self.stroke_path(&path);
+    }
+
+    /// fill a polygon, closed automatically, using `rule` where it crosses itself
+    pub fn fill_polygon(&mut self, points: &[(f32, f32)], rule: FillRule) {
+        if points.len() < 3 {
             return;
         }
 
-        let (x_extra, y_extra) = (
-            (x + width).saturating_sub(self.width),
-            (y + height).saturating_sub(self.height)
-        );
+        let (color, t) = (self.fill_color, self.transform);
+        let points = points.iter().map(|p| t.apply(p.0, p.1)).collect();
+        self.fill_contours(&[points], rule, color);
+    }
+
+    /// forget the current path and start a new, empty one
+    pub fn begin_path(&mut self) {
+        self.path = Path::new();
+    }
+
+    /// start a new subpath of the current path at `(x, y)`
+    pub fn move_to(&mut self, x: f32, y: f32) {
+        self.add_to_path(|path| path.move_to(x, y));
+    }
+
+    /// add a straight line to the current path
+    pub fn line_to(&mut self, x: f32, y: f32) {
+        self.add_to_path(|path| path.line_to(x, y));
+    }
+
+    /// add a quadratic bézier curve to the current path
+    pub fn quadratic_curve_to(&mut self, cpx: f32, cpy: f32, x: f32, y: f32) {
+        self.add_to_path(|path| path.quadratic_curve_to(cpx, cpy, x, y));
+    }
+
+    /// add a cubic bézier curve to the current path
+    pub fn bezier_curve_to(&mut self, cp1x: f32, cp1y: f32, cp2x: f32, cp2y: f32, x: f32, y: f32) {
+        self.add_to_path(|path| path.bezier_curve_to(cp1x, cp1y, cp2x, cp2y, x, y));
+    }
+
+    /// add a circular arc to the current path, see `Path::arc`
+    pub fn arc(&mut self, x: f32, y: f32, radius: f32, start: f32, end: f32, anticlockwise: bool) {
+        self.add_to_path(|path| path.arc(x, y, radius, start, end, anticlockwise));
+    }
+
+    /// add an arc touching two lines to the current path, see `Path::arc_to`
+    pub fn arc_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32) {
+        self.add_to_path(|path| path.arc_to(x1, y1, x2, y2, radius));
+    }
 
-        let width = width - x_extra;
-        let height = height - y_extra;
+    /// add a closed rectangle to the current path
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        self.add_to_path(|path| path.rect(x, y, width, height));
+    }
+
+    /// add a closed rectangle with rounded corners to the current path
+    ///
+    /// see `Path::round_rect` for how `radii` are used.
+    pub fn round_rect(&mut self, x: f32, y: f32, width: f32, height: f32, radii: &[f32]) {
+        self.add_to_path(|path| path.round_rect(x, y, width, height, radii));
+    }
 
-        let data: Vec<_> = (0..width*height).map(|_| {
-            self.fill_color.into_gpu(None)
-        }).collect();
+    /// close the current subpath of the current path
+    pub fn close_path(&mut self) {
+        self.path.close_path();
+    }
 
-        self.window.update_canvas(x, y, width, height, &data);
+    /// fill the current path with the fill color, using the fill rule
+    pub fn fill(&mut self) {
+        let (contours, rule, color) = (self.path.fill_contours(), self.fill_rule, self.fill_color);
+        self.fill_contours(&contours, rule, color);
     }
 
-    /// fill rectangle using a fill function 
+    /// stroke the current path with the stroke color and line width
     ///
-    /// 
+    /// the line width and dashes are scaled by the current transform.
+    pub fn stroke(&mut self) {
+        let contours = self.path.stroke_contours(&self.stroke_style, &self.transform, self.visible());
+        let color = self.stroke_color;
+        self.fill_contours(&contours, FillRule::NonZero, color);
+    }
 
-    pub fn fill_by<C, F>(&mut self, x: u32, y: u32, width: u32, height: u32, f: F) 
-        where F: Fn(u32, u32) -> C,
-              C: CanvasColor
+    /// fill a path with the fill color, using the fill rule
+    pub fn fill_path(&mut self, path: &Path) {
+        let (rule, color) = (self.fill_rule, self.fill_color);
+        self.fill_contours(&path.transformed(&self.transform).fill_contours(), rule, color);
+    }
+
+    /// stroke a path with the stroke color and line width
+    pub fn stroke_path(&mut self, path: &Path) {
+        let contours = path.transformed(&self.transform)
+            .stroke_contours(&self.stroke_style, &self.transform, self.visible());
+        let color = self.stroke_color;
+        self.fill_contours(&contours, FillRule::NonZero, color);
+    }
+
+    /// the canvas, as its top left and bottom right corner
+    fn visible(&self) -> (Point, Point) {
+        ((0.0, 0.0), (self.width as f32, self.height as f32))
+    }
+
+    /// add to the current path in coordinates of the current transform, with
+    /// `add` building onto a path that starts at the current point
+    fn add_to_path<F: FnOnce(&mut Path)>(&mut self, add: F) {
+        let mut path = Path::new();
+        let current = match (self.path.current_point(), self.transform.invert()) {
+            (Some(p), Some(inverse)) => {
+                let (x, y) = inverse.apply(p.0, p.1);
+                path.move_to(x, y);
+                true
+            }
+            _ => false,
+        };
+
+        add(&mut path);
+        self.path.extend(&path.transformed(&self.transform), current);
+    }
+
+    /// rasterize closed polygons and paint them, clipped to the canvas
+    fn fill_contours(&mut self, contours: &[Vec<Point>], rule: FillRule, color: color::Rgba) {
+        let antialias = self.antialias;
+        let pixels = self.rasterize(contours, rule, antialias);
+        self.paint(pixels, color);
+    }
+
+    /// the pixels covered by closed polygons, clipped to the canvas
+    fn rasterize(&self, contours: &[Vec<Point>], rule: FillRule, antialias: bool) -> Vec<Coverage> {
+        // a shape with a point that's nowhere can't be drawn sensibly
+        let contours: Vec<_> = contours.iter()
+            .filter(|c| c.iter().all(|p| p.0.is_finite() && p.1.is_finite()))
+            .cloned()
+            .collect();
+
+        let points = || contours.iter().flat_map(|c| c.iter());
+        if points().next().is_none() {
+            return Vec::new();
+        }
+
+        let min_x = points().fold(::std::f32::INFINITY, |m, p| m.min(p.0));
+        let min_y = points().fold(::std::f32::INFINITY, |m, p| m.min(p.1));
+        let max_x = points().fold(::std::f32::NEG_INFINITY, |m, p| m.max(p.0));
+        let max_y = points().fold(::std::f32::NEG_INFINITY, |m, p| m.max(p.1));
+
+        let (x0, y0) = ((min_x.floor() as i64).max(0), (min_y.floor() as i64).max(0));
+        let x1 = (max_x.ceil() as i64).min(self.width as i64);
+        let y1 = (max_y.ceil() as i64).min(self.height as i64);
+
+        if x0 >= x1 || y0 >= y1 {
+            return Vec::new();
+        }
+
+        raster::polygon(&contours, rule, x0, y0, x1, y1, antialias)
+    }
+
+    /// blend rasterized pixels with a color, according to their coverage
+    ///
+    /// without antialiasing every pixel counts as fully covered.
+    fn paint(&mut self, pixels: Vec<Coverage>, color: color::Rgba) {
+        let antialias = self.antialias;
+
+        self.update_pixels(pixels, |c, d| {
+            let c = if antialias { c } else { 1.0 };
+            color::blend(color.with_coverage(c), d)
+        });
+    }
+
+    /// replace rasterized pixels with `f(coverage, current color)`
+    ///
+    /// coverage of pixels that appear more than once is added up.
+    fn update_pixels<F>(&mut self, mut pixels: Vec<Coverage>, f: F)
+        where F: Fn(f32, [u8; 4]) -> [u8; 4]
     {
-        if x >= self.width || y >= self.height {
+        let (w, h) = (self.width as i64, self.height as i64);
+        pixels.retain(|&(x, y, _)| x >= 0 && y >= 0 && x < w && y < h);
+
+        if pixels.is_empty() {
+            return;
+        }
+
+        let x0 = pixels.iter().map(|p| p.0).min().unwrap();
+        let y0 = pixels.iter().map(|p| p.1).min().unwrap();
+        let x1 = pixels.iter().map(|p| p.0).max().unwrap() + 1;
+        let y1 = pixels.iter().map(|p| p.1).max().unwrap() + 1;
+        let width = (x1 - x0) as usize;
+
+        let mut coverage = vec![0.0f32; width * (y1 - y0) as usize];
+        for (x, y, c) in pixels {
+            coverage[(y - y0) as usize * width + (x - x0) as usize] += c;
+        }
+
+        let mut data = self.backend.read_back(x0 as u32, y0 as u32, width as u32, (y1 - y0) as u32);
+
+        for (d, &c) in data.iter_mut().zip(&coverage) {
+            if c > 0.0 {
+                *d = f(c, *d);
+            }
+        }
+
+        self.backend.update_canvas(x0 as u32, y0 as u32, width as u32, (y1 - y0) as u32, &data);
+    }
+
+    /// fill the part of a rectangle that lies on the canvas with one color
+    fn paint_rect(&mut self, x: i64, y: i64, width: i64, height: i64, color: [u8; 4]) {
+        let (x0, y0) = (x.max(0), y.max(0));
+        let x1 = (x + width).min(self.width as i64);
+        let y1 = (y + height).min(self.height as i64);
+
+        if x0 >= x1 || y0 >= y1 {
             return;
         }
 
-        let (x_extra, y_extra) = (
-            (x + width).saturating_sub(self.width),
-            (x + height).saturating_sub(self.height)
-        );
+        let (width, height) = ((x1 - x0) as u32, (y1 - y0) as u32);
+        let data = vec![color; pixel_count(width, height)];
+
+        self.backend.update_canvas(x0 as u32, y0 as u32, width, height, &data);
+    }
+
+    /// fill the part of a rectangle on the canvas with colors from a function of
+    /// canvas coordinates
+    pub fn fill_by<C, F>(&mut self, x: i32, y: i32, width: u32, height: u32, f: F)
+        where F: Fn(u32, u32) -> C,
+              C: CanvasColor
+    {
+        let (x0, y0) = ((x as i64).max(0), (y as i64).max(0));
+        let x1 = (x as i64 + width as i64).min(self.width as i64);
+        let y1 = (y as i64 + height as i64).min(self.height as i64);
 
-        let width = width - x_extra;
-        let height = height - y_extra;
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
 
+        let (x0, y0, x1, y1) = (x0 as u32, y0 as u32, x1 as u32, y1 as u32);
         let mut data: Vec<_> = Vec::new();
 
-        for j in y .. y + height {
-            for i in x .. x + width {
+        for j in y0 .. y1 {
+            for i in x0 .. x1 {
                 let color = f(i, j).into_gpu(None);
                 data.push(color);
             }
         }
 
-        self.window.update_canvas(x, y, width, height, &data);
+        self.backend.update_canvas(x0, y0, x1 - x0, y1 - y0, &data);
+    }
+
+    /// `(x, y)` as canvas coordinates, if it lies on the canvas
+    fn on_canvas(&self, x: i32, y: i32) -> Option<(u32, u32)> {
+        if x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height {
+            Some((x as u32, y as u32))
+        } else {
+            None
+        }
     }
 
     /// set a pixel to a specific color
     ///
     /// this is a low level texture operation and is not effected
     /// by preset colors or alpha blending
-    pub fn set_pixel<C: CanvasColor>(&mut self, x: u32, y: u32, c: C) {
-        if x >= self.width || y >= self.height {
+    pub fn set_pixel<C: CanvasColor>(&mut self, x: i32, y: i32, c: C) {
+        if let Some((x, y)) = self.on_canvas(x, y) {
+            let data = [c.into_gpu(None)];
+            self.backend.update_canvas(x, y, 1, 1, &data);
+        }
+    }
+
+    /// get the color of a single pixel
+    ///
+    /// returns `None` if `(x, y)` is outside of the canvas
+    pub fn get_pixel(&self, x: i32, y: i32) -> Option<[u8; 4]> {
+        self.on_canvas(x, y).map(|(x, y)| self.backend.read_back(x, y, 1, 1)[0])
+    }
+
+    /// read back a rectangle of pixels
+    ///
+    /// pixels outside of the canvas are transparent black
+    pub fn get_image_data(&self, x: i32, y: i32, width: u32, height: u32) -> ImageData {
+        let mut img = ImageData::new(width, height);
+
+        // the part on the canvas
+        let (x0, y0) = ((x as i64).max(0), (y as i64).max(0));
+        let x1 = (x as i64 + width as i64).min(self.width as i64);
+        let y1 = (y as i64 + height as i64).min(self.height as i64);
+
+        if x0 >= x1 || y0 >= y1 {
+            return img;
+        }
+
+        let (inner_width, inner_height) = ((x1 - x0) as usize, (y1 - y0) as u32);
+        let inner = self.backend.read_back(x0 as u32, y0 as u32, inner_width as u32, inner_height);
+
+        for j in 0..inner_height as usize {
+            let src = j * inner_width;
+            let dst = (j + (y0 - y as i64) as usize) * width as usize + (x0 - x as i64) as usize;
+
+            img.pixels[dst .. dst + inner_width].copy_from_slice(&inner[src .. src + inner_width]);
+        }
+
+        img
+    }
+
+    /// copy an image onto the canvas with its top left corner at `(x, y)`
+    ///
+    /// like `set_pixel`, this replaces the pixels on the canvas outright, without
+    /// any alpha blending
+    pub fn put_image_data(&mut self, img: &ImageData, x: i32, y: i32) {
+        self.put_image_data_dirty(img, x, y, 0, 0, img.width, img.height);
+    }
+
+    /// copy only the `dirty_width` x `dirty_height` rectangle at `(dirty_x, dirty_y)`
+    /// of an image onto the canvas
+    ///
+    /// the image is still positioned with its top left corner at `(x, y)`, so
+    /// the copied pixels end up at `(x + dirty_x, y + dirty_y)`.
+    pub fn put_image_data_dirty(&mut self, img: &ImageData, x: i32, y: i32,
+        dirty_x: u32, dirty_y: u32, dirty_width: u32, dirty_height: u32)
+    {
+        // the dirty rectangle in image coordinates, clipped to the image
+        // and then to the canvas
+        let (x, y) = (x as i64, y as i64);
+        let left = (dirty_x as i64).max(-x);
+        let top = (dirty_y as i64).max(-y);
+        let right = (dirty_x as i64 + dirty_width as i64).min(img.width as i64).min(self.width as i64 - x);
+        let bottom = (dirty_y as i64 + dirty_height as i64).min(img.height as i64).min(self.height as i64 - y);
+
+        if left >= right || top >= bottom {
             return;
         }
-        
-        let data = [c.into_gpu(None)];
 
-        self.window.update_canvas(x, y, 1, 1, &data);
+        let mut data = Vec::with_capacity(((right - left) * (bottom - top)) as usize);
+
+        for j in top .. bottom {
+            let row = img.row(j as u32);
+            data.extend_from_slice(&row[left as usize .. right as usize]);
+        }
+
+        self.backend.update_canvas((x + left) as u32, (y + top) as u32,
+            (right - left) as u32, (bottom - top) as u32, &data);
+    }
+
+    /// draw an image or another `Context` with its top left corner at `(x, y)`
+    ///
+    /// unlike `put_image_data`, the image is alpha blended with what's
+    /// already on the canvas, and resampled if it lands between pixels
+    pub fn draw_image<I: ImageSource>(&mut self, img: &I, x: f32, y: f32) {
+        let img = img.image_data();
+        let t = self.transform;
+        let (left, top) = t.apply(x, y);
+
+        if Transform::translation(t.e, t.f) == t && left.fract() == 0.0 && top.fract() == 0.0 {
+            self.composite(&img, left as i64, top as i64);
+        } else {
+            let (w, h) = (img.width as f32, img.height as f32);
+            self.draw_image_sub(&*img, 0.0, 0.0, w, h, x, y, w, h);
+        }
+    }
+
+    /// draw an image scaled to fill the `width` x `height` rectangle at `(x, y)`
+    pub fn draw_image_scaled<I: ImageSource>(&mut self, img: &I, x: f32, y: f32, width: f32, height: f32) {
+        let img = img.image_data();
+        let (w, h) = (img.width as f32, img.height as f32);
+        self.draw_image_sub(&*img, 0.0, 0.0, w, h, x, y, width, height);
+    }
+
+    /// draw the `sw` x `sh` region at `(sx, sy)` of an image, scaled to fill the
+    /// `dw` x `dh` rectangle at `(dx, dy)`
+    ///
+    /// images turned or mirrored by the transform are sampled at most bilinearly
+    pub fn draw_image_sub<I: ImageSource>(&mut self, img: &I, sx: f32, sy: f32, sw: f32, sh: f32,
+        dx: f32, dy: f32, dw: f32, dh: f32)
+    {
+        let (img, t) = (img.image_data(), self.transform);
+        let (left, top) = t.apply(dx, dy);
+        let (right, bottom) = t.apply(dx + dw, dy + dh);
+
+        if t.is_axis_aligned() && t.a > 0.0 && t.d > 0.0 {
+            let (x, y, scaled) = resample::place(&img, (sx, sy, sw, sh), (left, top, right - left, bottom - top),
+                (self.width, self.height), self.image_filter);
+
+            self.composite(&scaled, x, y);
+            return;
+        }
+
+        // turned or mirrored, so every pixel is looked up on its own
+        let to_source = match t.invert() {
+            Some(inverse) => inverse
+                .then(&Transform::translation(-dx, -dy))
+                .then(&Transform::scaling(sw / dw, sh / dh))
+                .then(&Transform::translation(sx, sy)),
+            None => return,
+        };
+
+        let corners = [(left, top), t.apply(dx + dw, dy), (right, bottom), t.apply(dx, dy + dh)];
+        let min_x = corners.iter().fold(::std::f32::INFINITY, |m, p| m.min(p.0));
+        let min_y = corners.iter().fold(::std::f32::INFINITY, |m, p| m.min(p.1));
+        let max_x = corners.iter().fold(::std::f32::NEG_INFINITY, |m, p| m.max(p.0));
+        let max_y = corners.iter().fold(::std::f32::NEG_INFINITY, |m, p| m.max(p.1));
+
+        let (x0, x1) = raster::pixel_range(min_x, max_x);
+        let (y0, y1) = raster::pixel_range(min_y, max_y);
+        let (x0, x1) = (x0.max(0), x1.min(self.width as i64));
+        let (y0, y1) = (y0.max(0), y1.min(self.height as i64));
+
+        if x0 < x1 && y0 < y1 {
+            let warped = resample::warp(&img, (sx, sy, sw, sh), &to_source, (x0, y0, x1, y1), self.image_filter);
+            self.composite(&warped, x0, y0);
+        }
+    }
+
+    /// alpha blend an image onto the canvas at `(x, y)`
+    fn composite(&mut self, img: &ImageData, x: i64, y: i64) {
+        // the part of the image on the canvas
+        let (left, top) = ((-x).max(0), (-y).max(0));
+        let right = (img.width as i64).min(self.width as i64 - x);
+        let bottom = (img.height as i64).min(self.height as i64 - y);
+
+        if left >= right || top >= bottom {
+            return;
+        }
+
+        let (width, height) = ((right - left) as usize, (bottom - top) as u32);
+        let (x, y) = ((x + left) as u32, (y + top) as u32);
+        let mut data = self.backend.read_back(x, y, width as u32, height);
+
+        for j in 0..height as usize {
+            let row = &img.row(j as u32 + top as u32)[left as usize ..];
+            for i in 0..width {
+                let k = j * width + i;
+                data[k] = color::blend(row[i], data[k]);
+            }
+        }
+
+        self.backend.update_canvas(x, y, width as u32, height, &data);
+    }
+
+    /// encode the contents of the canvas as a png
+    pub fn write_png<W: Write>(&self, w: W) -> io::Result<()> {
+        self.get_image_data(0, 0, self.width, self.height).write_png(w)
+    }
+
+    /// save the contents of the canvas to a png file
+    pub fn save_png<P: AsRef<FilePath>>(&self, path: P) -> io::Result<()> {
+        self.get_image_data(0, 0, self.width, self.height).save_png(path)
+    }
+
+    /// encode the contents of the canvas in any supported image format
+    pub fn write_image<W: Write>(&self, w: W, format: ImageFormat) -> io::Result<()> {
+        self.get_image_data(0, 0, self.width, self.height).write_to(w, format)
+    }
+
+    /// save the contents of the canvas, picking the format from the file extension
+    pub fn save<P: AsRef<FilePath>>(&self, path: P) -> io::Result<()> {
+        self.get_image_data(0, 0, self.width, self.height).save(path)
+    }
+}
+
+impl ImageSource for Context {
+    fn image_data<'a>(&'a self) -> Cow<'a, ImageData> {
+        Cow::Owned(self.get_image_data(0, 0, self.width, self.height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::{Background, Context};
+    use image::ImageData;
+    use path::Path;
+    use raster::{Connectivity, FillRule};
+    use transform::Transform;
+
+    #[test]
+    fn headless_readback() {
+        let mut ctx = Context::headless(16, 16);
+
+        ctx.fill_color((1.0, 0.0, 0.0));
+        ctx.fill_rect(4.0, 4.0, 100.0, 2.0);
+        ctx.set_pixel(0, 0, [0u8, 0, 0xFF, 0xFF]);
+
+        assert_eq!(ctx.get_pixel(0, 0), Some([0, 0, 0xFF, 0xFF]));
+        assert_eq!(ctx.get_pixel(15, 5), Some([0xFF, 0, 0, 0xFF]));
+        assert_eq!(ctx.get_pixel(14, 6), Some([0, 0, 0, 0]));
+        assert_eq!(ctx.get_pixel(16, 5), None);
+
+        let data = ctx.get_image_data(14, 4, 4, 1);
+        assert_eq!(data.pixels, vec![[0xFF, 0, 0, 0xFF], [0xFF, 0, 0, 0xFF], [0; 4], [0; 4]]);
+    }
+
+    #[test]
+    fn put_image_data() {
+        let mut ctx = Context::headless(8, 8);
+        let mut img = ImageData::new(4, 4);
+
+        for (i, p) in img.pixels.iter_mut().enumerate() {
+            *p = [i as u8, 0, 0, 0xFF];
+        }
+
+        ctx.put_image_data(&img, 6, 6);
+        assert_eq!(ctx.get_image_data(6, 6, 2, 2).pixels,
+                   vec![[0, 0, 0, 0xFF], [1, 0, 0, 0xFF], [4, 0, 0, 0xFF], [5, 0, 0, 0xFF]]);
+
+        ctx.put_image_data_dirty(&img, 0, 0, 1, 2, 1, 1);
+        assert_eq!(ctx.get_pixel(1, 2), Some([9, 0, 0, 0xFF]));
+        assert_eq!(ctx.get_pixel(0, 2), Some([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn headless_drawing() {
+        let mut ctx = Context::headless(4, 3);
+
+        ctx.fill_color((0.0, 0.0, 1.0));
+        ctx.fill_rect(0.0, 0.0, 4.0, 1.0);
+        ctx.fill_by(0, 1, 4, 2, |x, y| [x as u8, y as u8, 0, 0xFF]);
+        ctx.set_pixel(3, 2, [1u8, 2, 3, 0xFF]);
+
+        let blue = [0, 0, 0xFF, 0xFF];
+        assert_eq!(ctx.get_image_data(0, 0, 4, 3).pixels, vec![
+            blue, blue, blue, blue,
+            [0, 1, 0, 0xFF], [1, 1, 0, 0xFF], [2, 1, 0, 0xFF], [3, 1, 0, 0xFF],
+            [0, 2, 0, 0xFF], [1, 2, 0, 0xFF], [2, 2, 0, 0xFF], [1, 2, 3, 0xFF],
+        ]);
+
+        // growing keeps what was drawn, and drawing is clipped to the canvas
+        ctx.resize(5, 4);
+        ctx.fill_rect(4.0, 3.0, 10.0, 10.0);
+        assert_eq!(ctx.dimensions(), (5, 4));
+        assert_eq!(ctx.get_image_data(3, 2, 2, 2).pixels, vec![
+            [1, 2, 3, 0xFF], [0; 4],
+            [0; 4], blue,
+        ]);
+    }
+
+    #[test]
+    fn draw_image_blends() {
+        let mut ctx = Context::headless(4, 4);
+        ctx.fill_color((0.0, 0.0, 1.0));
+        ctx.fill_rect(0.0, 0.0, 4.0, 4.0);
+
+        let img = ImageData::from_pixels(3, 1, vec![
+            [0xFF, 0, 0, 0xFF], [0xFF, 0, 0, 0x80], [0xFF, 0, 0, 0],
+        ]);
+        ctx.draw_image(&img, 1.0, 1.0);
+
+        assert_eq!(ctx.get_image_data(0, 1, 4, 1).pixels, vec![
+            [0, 0, 0xFF, 0xFF], [0xFF, 0, 0, 0xFF], [0x80, 0, 0x7F, 0xFF], [0, 0, 0xFF, 0xFF],
+        ]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn offscreen() {
+        let mut ctx = Context::headless(8, 8);
+        let mut sprite = ctx.create_offscreen(3, 3);
+
+        assert_eq!(sprite.get_pixel(1, 1), Some([0, 0, 0, 0]));
+
+        sprite.fill_color((1.0, 1.0, 0.0));
+        sprite.fill_rect(1.0, 1.0, 1.0, 1.0);
+        ctx.draw_image(&sprite, 4.0, 4.0);
+
+        // only the opaque pixel of the sprite is drawn
+        assert_eq!(ctx.get_pixel(5, 5), Some([0xFF, 0xFF, 0, 0xFF]));
+        assert_eq!(ctx.get_pixel(4, 4), Some([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn recording() {
+        let mut ctx = Context::headless(8, 8);
+        assert!(ctx.write_recording(Vec::new()).is_err());
+
+        ctx.start_recording();
+        ctx.fill_color((1.0, 0.0, 0.0));
+        ctx.fill_rect(2.0, 2.0, 3.0, 3.0);
+        ctx.present();
+
+        let mut gif = Vec::new();
+        ctx.write_recording(&mut gif).unwrap();
+
+        assert!(!ctx.is_recording());
+        assert!(gif.starts_with(b"GIF89a\x08\x00\x08\x00"));
+        assert_eq!(gif.last(), Some(&0x3B));
+    }
+
+    #[test]
+    fn video_frames_follow_the_virtual_clock() {
+        let path = ::std::env::temp_dir().join(format!("kansas-video-{}.y4m", ::std::process::id()));
+
+        let mut ctx = Context::headless(4, 3);
+        ctx.frame_rate(25);
+        ctx.start_video_file(&path, 25).unwrap();
+
+        for i in 0..3 {
+            ctx.set_pixel(i, 0, (1.0, 1.0, 1.0));
+            ctx.present();
+        }
+        ctx.stop_video().unwrap();
+        assert!(ctx.stop_video().is_err());
+
+        let data = ::std::fs::read(&path).unwrap();
+        ::std::fs::remove_file(&path).unwrap();
+
+        let header = b"YUV4MPEG2 W4 H3 F25:1 Ip A1:1 C420jpeg XCOLORRANGE=FULL\n";
+        assert!(data.starts_with(header));
+
+        // the starting frame and one per present, each with 12 luma samples
+        // and two 2x2 chroma planes
+        let frame = b"FRAME\n".len() + 12 + 2 * 4;
+        assert_eq!(data.len(), header.len() + 4 * frame);
+        assert_eq!(data[header.len() + 6], 0);
+        assert_eq!(data[header.len() + 6 + frame], 0xFF);
+    }
+
+    #[test]
+    fn stroke_rect() {
+        let mut ctx = Context::headless(12, 12);
+        ctx.fill_rect(0.0, 0.0, 12.0, 12.0);
+        ctx.stroke_color((1.0, 1.0, 1.0));
+
+        let white = |ctx: &Context, y| -> Vec<i32> {
+            (0..12).filter(|&x| ctx.get_pixel(x, y) == Some([0xFF; 4])).collect()
+        };
+
+        ctx.stroke_rect(2.0, 2.0, 6.0, 6.0);
+        assert_eq!(white(&ctx, 5), vec![2, 7]);
+        assert_eq!(ctx.get_pixel(4, 2), Some([0xFF; 4]));
+        assert_eq!(ctx.get_pixel(4, 3), Some([0, 0, 0, 0xFF]));
+
+        // wider lines straddle the edge, and get clipped to the canvas
+        ctx.fill_rect(0.0, 0.0, 12.0, 12.0);
+        ctx.line_width(4.0);
+        ctx.stroke_rect(0.0, 4.0, 8.0, 7.0);
+        assert_eq!(white(&ctx, 7), vec![0, 1, 6, 7, 8, 9]);
+        assert_eq!(ctx.get_pixel(9, 2), Some([0xFF; 4]));
+        assert_eq!(ctx.get_pixel(10, 2), Some([0, 0, 0, 0xFF]));
+        assert_eq!(ctx.get_pixel(4, 8), Some([0, 0, 0, 0xFF]));
+        assert_eq!(ctx.get_pixel(4, 11), Some([0xFF; 4]));
+    }
+
+    #[test]
+    fn translucent_rects() {
+        let mut ctx = Context::headless(4, 4);
+        ctx.fill_color((0.0, 0.0, 1.0));
+        ctx.fill_rect(0.0, 0.0, 4.0, 4.0);
+
+        ctx.fill_color((1.0, 0.0, 0.0, 0.5));
+        ctx.fill_rect(0.0, 0.0, 2.0, 1.0);
+        ctx.stroke_color((1.0, 0.0, 0.0, 0.5));
+        ctx.stroke_rect(0.0, 2.0, 4.0, 2.0);
+
+        let half = [0x80, 0, 0x7F, 0xFF];
+        assert_eq!(ctx.get_image_data(0, 0, 4, 1).pixels, vec![half, half, [0, 0, 0xFF, 0xFF], [0, 0, 0xFF, 0xFF]]);
+        assert_eq!(ctx.get_image_data(0, 2, 4, 1).pixels, vec![half; 4]);
+    }
+
+    #[test]
+    fn draw_line() {
+        let mut ctx = Context::headless(8, 8);
+        ctx.fill_rect(0.0, 0.0, 8.0, 8.0);
+        ctx.stroke_color((1.0, 1.0, 1.0));
+
+        // clipped at the edge of the canvas
+        ctx.draw_line(-2.5, 1.5, 5.5, 1.5);
+        assert_eq!(ctx.get_image_data(0, 1, 8, 1).pixels, [
+            [0xFF; 4], [0xFF; 4], [0xFF; 4], [0xFF; 4], [0xFF; 4], [0xFF; 4],
+            [0, 0, 0, 0xFF], [0, 0, 0, 0xFF],
+        ].to_vec());
+
+        // halfway between two rows, both get half of the line
+        ctx.antialias(true);
+        ctx.draw_line(1.5, 5.0, 6.5, 5.0);
+        assert_eq!(ctx.get_pixel(3, 4), Some([0x80, 0x80, 0x80, 0xFF]));
+        assert_eq!(ctx.get_pixel(3, 5), Some([0x80, 0x80, 0x80, 0xFF]));
+        assert_eq!(ctx.get_pixel(3, 6), Some([0, 0, 0, 0xFF]));
+    }
+    #[test]
+    fn translucent_lines() {
+        let mut ctx = Context::headless(4, 4);
+        ctx.fill_color((0.0, 0.0, 1.0));
+        ctx.fill_rect(0.0, 0.0, 4.0, 4.0);
+        ctx.stroke_color((1.0, 0.0, 0.0, 0.5));
+
+        // blended either way, antialiasing only changes the coverage
+        let half = [0x80, 0, 0x7F, 0xFF];
+        for &(antialias, y) in &[(false, 1.5), (true, 2.5)] {
+            ctx.antialias(antialias);
+            ctx.draw_line(-1.0, y, 5.0, y);
+            assert_eq!(ctx.get_image_data(0, y as i32, 4, 1).pixels, vec![half; 4]);
+        }
+    }
+
+    #[test]
+    fn far_off_lines() {
+        let mut ctx = Context::headless(16, 16);
+        ctx.fill_rect(0.0, 0.0, 16.0, 16.0);
+        let white = [0xFF, 0xFF, 0xFF, 0xFF];
+        ctx.stroke_color((1.0, 1.0, 1.0));
+
+        // only the part on the canvas is rasterized
+        ctx.draw_line(0.0, 0.5, 2e8, 0.5);
+        ctx.draw_line(-1e30, 3.5, 1e30, 3.5);
+        assert_eq!(ctx.get_image_data(0, 0, 16, 1).pixels, vec![white; 16]);
+        assert_eq!(ctx.get_image_data(0, 3, 16, 1).pixels, vec![white; 16]);
+
+        ctx.antialias(true);
+        ctx.draw_line(-2e8, 6.5, 2e8, 6.5);
+        assert_eq!(ctx.get_image_data(0, 6, 16, 1).pixels, vec![white; 16]);
+
+        // lines that aren't finite, or miss the canvas, draw nothing
+        ctx.draw_line(0.0, 12.5, ::std::f32::INFINITY, 12.5);
+        ctx.draw_line(::std::f32::NAN, 10.0, 5.0, 15.0);
+        ctx.draw_line(-1e9, -5.0, 1e9, -5.0);
+        assert_eq!(ctx.get_image_data(0, 7, 16, 9).pixels, vec![[0, 0, 0, 0xFF]; 16 * 9]);
+
+        // dashes keep their place along the whole line
+        ctx.antialias(false);
+        ctx.set_line_dash(&[2.0, 2.0]);
+        ctx.draw_line(-1e6, 9.5, 16.0, 9.5);
+        let row: Vec<_> = ctx.get_image_data(0, 9, 8, 1).pixels.iter().map(|p| p[0] / 0xFF).collect();
+        assert_eq!(row, vec![1, 1, 0, 0, 1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn circles() {
+        let mut ctx = Context::headless(12, 12);
+        ctx.stroke_color((1.0, 1.0, 1.0));
+
+        let clear = |ctx: &mut Context| {
+            ctx.fill_color((0.0, 0.0, 0.0));
+            ctx.fill_rect(0.0, 0.0, 12.0, 12.0);
+            ctx.fill_color((1.0, 1.0, 1.0));
+        };
+
+        let count = |ctx: &Context| {
+            ctx.get_image_data(0, 0, 12, 12).pixels.iter().filter(|&&p| p == [0xFF; 4]).count()
+        };
+
+        // a 4x4 block without its corners
+        clear(&mut ctx);
+        ctx.fill_circle(4.0, 4.0, 2.0);
+        assert_eq!(count(&ctx), 12);
+        assert_eq!(ctx.get_pixel(2, 2), Some([0, 0, 0, 0xFF]));
+
+        // the outline leaves the middle alone
+        clear(&mut ctx);
+        ctx.stroke_circle(6.0, 6.0, 4.0);
+        assert_eq!(ctx.get_pixel(5, 5), Some([0, 0, 0, 0xFF]));
+        assert_eq!(ctx.get_pixel(9, 5), Some([0xFF; 4]));
+
+        // the right half of a pie
+        clear(&mut ctx);
+        ctx.fill_arc(6.0, 6.0, 4.0, -FRAC_PI_2, FRAC_PI_2);
+        assert_eq!(ctx.get_pixel(7, 6), Some([0xFF; 4]));
+        assert_eq!(ctx.get_pixel(4, 6), Some([0, 0, 0, 0xFF]));
+
+        // antialiased edges are partly covered
+        clear(&mut ctx);
+        ctx.antialias(true);
+        ctx.fill_circle(6.0, 6.0, 3.0);
+        let edge = ctx.get_pixel(8, 7).unwrap();
+        assert!(edge[0] > 0 && edge[0] < 0xFF);
+        assert_eq!(ctx.get_pixel(6, 6), Some([0xFF; 4]));
+    }
+
+    #[test]
+    fn fill_polygon() {
+        let mut ctx = Context::headless(8, 8);
+        ctx.fill_rect(0.0, 0.0, 8.0, 8.0);
+        ctx.fill_color((1.0, 1.0, 1.0));
+
+        // a concave "u", hanging off the left edge of the canvas
+        ctx.fill_polygon(&[(-2.0, 1.0), (2.0, 1.0), (2.0, 4.0), (4.0, 4.0), (4.0, 1.0),
+            (6.0, 1.0), (6.0, 6.0), (-2.0, 6.0)], FillRule::EvenOdd);
+
+        assert_eq!(ctx.get_pixel(0, 1), Some([0xFF; 4]));
+        assert_eq!(ctx.get_pixel(3, 2), Some([0, 0, 0, 0xFF]));
+        assert_eq!(ctx.get_pixel(3, 5), Some([0xFF; 4]));
+        assert_eq!(ctx.get_pixel(6, 5), Some([0, 0, 0, 0xFF]));
+    }
+
+    #[test]
+    fn translucent_fills() {
+        let mut ctx = Context::headless(4, 4);
+        ctx.fill_color((0.0, 0.0, 1.0));
+        ctx.fill_rect(0.0, 0.0, 4.0, 4.0);
+        ctx.fill_color((1.0, 0.0, 0.0, 0.5));
+
+        // blended either way, and edges on pixel boundaries cover them fully
+        let half = [0x80, 0, 0x7F, 0xFF];
+        for &(antialias, y) in &[(false, 0.0), (true, 2.0)] {
+            ctx.antialias(antialias);
+            ctx.fill_polygon(&[(0.0, y), (4.0, y), (4.0, y + 2.0), (0.0, y + 2.0)], FillRule::NonZero);
+            assert_eq!(ctx.get_image_data(0, y as i32, 4, 2).pixels, vec![half; 8]);
+        }
+    }
+
+    #[test]
+    fn paths() {
+        let mut ctx = Context::headless(16, 16);
+        ctx.fill_rect(0.0, 0.0, 16.0, 16.0);
+        ctx.fill_color((1.0, 1.0, 1.0));
+
+        // a square with a hole, from two subpaths wound the same way
+        ctx.begin_path();
+        ctx.rect(1.0, 1.0, 10.0, 10.0);
+        ctx.rect(4.0, 4.0, 4.0, 4.0);
+        ctx.fill_rule(FillRule::EvenOdd);
+        ctx.fill();
+
+        assert_eq!(ctx.get_pixel(2, 2), Some([0xFF; 4]));
+        assert_eq!(ctx.get_pixel(5, 5), Some([0, 0, 0, 0xFF]));
+
+        // a reusable path, stroked with a three pixel wide line
+        let mut path = Path::new();
+        path.move_to(13.5, 1.0);
+        path.line_to(13.5, 15.0);
+
+        ctx.stroke_color((1.0, 0.0, 0.0));
+        ctx.line_width(3.0);
+        ctx.stroke_path(&path);
+
+        let row = ctx.get_image_data(11, 8, 5, 1).pixels;
+        assert_eq!(row, vec![[0, 0, 0, 0xFF], [0xFF, 0, 0, 0xFF], [0xFF, 0, 0, 0xFF],
+            [0xFF, 0, 0, 0xFF], [0, 0, 0, 0xFF]]);
+    }
+
+    #[test]
+    fn dashed_strokes() {
+        let mut ctx = Context::headless(12, 4);
+        ctx.fill_rect(0.0, 0.0, 12.0, 4.0);
+        ctx.stroke_color((1.0, 1.0, 1.0));
+
+        let row = |ctx: &Context, y| -> Vec<u8> {
+            ctx.get_image_data(0, y, 12, 1).pixels.iter().map(|p| p[0] / 0xFF).collect()
+        };
+
+        ctx.set_line_dash(&[3.0, 1.0]);
+        assert_eq!(ctx.line_dash(), &[3.0, 1.0]);
+        ctx.draw_line(0.5, 0.5, 11.5, 0.5);
+        assert_eq!(row(&ctx, 0), vec![1, 1, 1, 0, 1, 1, 1, 0, 1, 1, 1, 1]);
+
+        ctx.set_line_dash(&[2.0]);
+        ctx.line_dash_offset(1.0);
+        ctx.begin_path();
+        ctx.move_to(0.0, 2.5);
+        ctx.line_to(12.0, 2.5);
+        ctx.stroke();
+        assert_eq!(row(&ctx, 2), vec![1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 1]);
+    }
+    #[test]
+    fn long_dashed_strokes() {
+        let mut ctx = Context::headless(12, 2);
+        ctx.fill_rect(0.0, 0.0, 12.0, 2.0);
+        ctx.stroke_color((1.0, 1.0, 1.0));
+
+        // a fine pattern along a long line only gets dashed near the canvas,
+        // where it covers about half of every pixel
+        ctx.antialias(true);
+        ctx.set_line_dash(&[0.01, 0.01]);
+        ctx.begin_path();
+        ctx.move_to(-1e6, 0.5);
+        ctx.line_to(1e6, 0.5);
+        ctx.stroke();
+        assert!(ctx.get_image_data(0, 0, 12, 1).pixels.iter().all(|p| p[0] > 0x70 && p[0] < 0x90));
+        ctx.antialias(false);
+
+        // and the dashes still line up with the start of the line
+        ctx.set_line_dash(&[2.0]);
+        ctx.line_dash_offset(1.0);
+        ctx.begin_path();
+        ctx.move_to(-4e6, 1.5);
+        ctx.line_to(12.0, 1.5);
+        ctx.stroke();
+        let row: Vec<_> = ctx.get_image_data(0, 1, 12, 1).pixels.iter().map(|p| p[0] / 0xFF).collect();
+        assert_eq!(row, vec![1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 1]);
+    }
+
+    #[test]
+    fn flood_fill() {
+        let mut ctx = Context::headless(8, 8);
+        ctx.fill_color((0.0, 0.0, 0.0));
+        ctx.fill_rect(0.0, 0.0, 8.0, 8.0);
+
+        // a white frame, with a slightly different pixel inside it
+        ctx.stroke_color((1.0, 1.0, 1.0));
+        ctx.stroke_rect(1.0, 1.0, 5.0, 5.0);
+        ctx.set_pixel(3, 3, 0x040404);
+
+        ctx.flood_fill(2, 2, (1.0, 0.0, 0.0), 0);
+        assert_eq!(ctx.get_pixel(2, 4), Some([0xFF, 0, 0, 0xFF]));
+        assert_eq!(ctx.get_pixel(3, 3), Some([4, 4, 4, 0xFF]));
+        assert_eq!(ctx.get_pixel(0, 0), Some([0, 0, 0, 0xFF]));
+
+        ctx.flood_fill(2, 2, (0.0, 0.0, 0.0), 0);
+        ctx.flood_fill(2, 2, (0.0, 1.0, 0.0), 4);
+        assert_eq!(ctx.get_pixel(3, 3), Some([0, 0xFF, 0, 0xFF]));
+
+        // without its corner, the inside touches the outside diagonally
+        ctx.flood_fill(2, 2, (0.0, 0.0, 0.0), 0);
+        ctx.set_pixel(1, 1, (0.0, 0.0, 0.0));
+        ctx.flood_fill(0, 0, (0.0, 0.0, 1.0), 0);
+        assert_eq!(ctx.get_pixel(2, 2), Some([0, 0, 0, 0xFF]));
+        ctx.flood_fill_with(0, 0, (0.0, 0.0, 0.0), 0, Connectivity::Eight);
+        ctx.flood_fill_with(0, 0, (0.0, 0.0, 1.0), 0, Connectivity::Eight);
+        assert_eq!(ctx.get_pixel(2, 2), Some([0, 0, 0xFF, 0xFF]));
+    }
+
+    #[test]
+    fn clear() {
+        let mut ctx = Context::headless(4, 4);
+        assert_eq!(ctx.get_pixel(1, 0), Some([0, 0, 0, 0]));
+
+        ctx.fill_color((1.0, 0.0, 0.0));
+        ctx.fill_rect(0.0, 0.0, 4.0, 4.0);
+        ctx.clear_rect(1.0, 1.0, 2.0, 5.0);
+        assert_eq!(ctx.get_pixel(1, 3), Some([0, 0, 0, 0]));
+        assert_eq!(ctx.get_pixel(0, 3), Some([0xFF, 0, 0, 0xFF]));
+
+        // the background doesn't change until something is cleared
+        ctx.background(Background::solid((0.0, 0.0, 1.0, 0.5)));
+        assert_eq!(ctx.get_pixel(1, 3), Some([0, 0, 0, 0]));
+
+        ctx.clear();
+        assert_eq!(ctx.get_pixel(0, 0), Some([0, 0, 0xFF, 0x80]));
+        assert_eq!(ctx.get_pixel(3, 3), Some([0, 0, 0xFF, 0x80]));
+
+        // growing the canvas fills the new space with background too
+        ctx.background(Background::Solid([0, 0xFF, 0, 0xFF]));
+        ctx.resize(6, 5);
+        assert_eq!(ctx.get_pixel(3, 3), Some([0, 0, 0xFF, 0x80]));
+        assert_eq!(ctx.get_pixel(5, 0), Some([0, 0xFF, 0, 0xFF]));
+        assert_eq!(ctx.get_pixel(0, 4), Some([0, 0xFF, 0, 0xFF]));
+    }
+    #[test]
+    fn round_rects() {
+        let mut ctx = Context::headless(8, 8);
+        ctx.fill_rect(0.0, 0.0, 8.0, 8.0);
+        ctx.fill_color((1.0, 1.0, 1.0));
+
+        // the curve only partly covers pixels near the corners
+        ctx.antialias(true);
+        ctx.fill_round_rect(0.0, 0.0, 8.0, 8.0, &[2.0]);
+        let corner = ctx.get_pixel(0, 0).unwrap();
+        assert!(corner[0] > 0 && corner[0] < 0xFF);
+        assert_eq!(ctx.get_pixel(4, 0), Some([0xFF; 4]));
+        assert_eq!(ctx.get_pixel(4, 4), Some([0xFF; 4]));
+
+        // without antialiasing the corner pixel's center is outside
+        ctx.antialias(false);
+        ctx.fill_color((0.0, 0.0, 0.0));
+        ctx.fill_rect(0.0, 0.0, 8.0, 8.0);
+        ctx.fill_color((1.0, 1.0, 1.0));
+        ctx.fill_round_rect(0.0, 0.0, 8.0, 8.0, &[2.0]);
+        assert_eq!(ctx.get_pixel(0, 0), Some([0, 0, 0, 0xFF]));
+        assert_eq!(ctx.get_pixel(4, 0), Some([0xFF; 4]));
+    }
+
+    #[test]
+    fn clipped_on_every_side() {
+        let mut ctx = Context::headless(4, 4);
+        let red = [0xFF, 0, 0, 0xFF];
+
+        // off the top left, and with a negative size off the bottom right
+        ctx.fill_color((1.0, 0.0, 0.0));
+        ctx.fill_rect(-2.0, -2.0, 3.0, 3.0);
+        ctx.fill_rect(5.0, 5.0, -2.0, -2.0);
+        assert_eq!(ctx.get_image_data(-1, -1, 6, 1).pixels, vec![[0; 4]; 6]);
+        assert_eq!(ctx.get_image_data(-1, 0, 6, 1).pixels, vec![[0; 4], red, [0; 4], [0; 4], [0; 4], [0; 4]]);
+        assert_eq!(ctx.get_pixel(3, 3), Some(red));
+        assert_eq!(ctx.get_pixel(-1, 3), None);
+
+        // only pixels with their center inside are filled
+        ctx.clear();
+        ctx.fill_rect(0.6, 0.0, 2.0, 1.0);
+        assert_eq!(ctx.get_image_data(0, 0, 3, 1).pixels, vec![[0; 4], red, red]);
+
+        ctx.clear();
+        let img = ImageData::from_pixels(2, 2, vec![[1, 2, 3, 0xFF], [4, 5, 6, 0xFF], [7, 8, 9, 0xFF], red]);
+        ctx.put_image_data(&img, -1, -1);
+        ctx.draw_image(&img, 3.0, 3.0);
+        assert_eq!(ctx.get_pixel(0, 0), Some(red));
+        assert_eq!(ctx.get_pixel(3, 3), Some([1, 2, 3, 0xFF]));
+
+        ctx.set_pixel(-1, 0, [0u8, 0, 0, 0xFF]);
+        assert_eq!(ctx.get_pixel(0, 0), Some(red));
+    }
+
+    #[test]
+    fn nothing_but_numbers() {
+        let mut ctx = Context::headless(4, 4);
+        let (nan, inf) = (::std::f32::NAN, ::std::f32::INFINITY);
+        let img = ImageData::from_pixels(1, 1, vec![[0xFF; 4]]);
+
+        // none of these mean anything, and none of them may panic
+        for &antialias in &[false, true] {
+            ctx.antialias(antialias);
+            for &v in &[nan, inf, -inf, 1e30, -1e30] {
+                ctx.fill_rect(v, 0.0, 4.0, 4.0);
+                ctx.fill_rect(0.0, 0.5, v, 2.0);
+                ctx.stroke_rect(0.0, v, 2.0, 2.0);
+                ctx.clear_rect(v, v, 1.0, 1.0);
+                ctx.fill_circle(1.0, 1.0, v);
+                ctx.stroke_ellipse(v, 1.0, 2.0, 2.0);
+                ctx.fill_round_rect(0.0, 0.0, 3.0, 3.0, &[v]);
+                ctx.fill_polygon(&[(0.0, 0.0), (v, 1.0), (1.0, 3.0)], FillRule::NonZero);
+                ctx.draw_line(0.0, 0.0, v, 1.0);
+                ctx.draw_image(&img, v, 0.5);
+                ctx.draw_image_scaled(&img, 0.0, 0.0, v, 2.0);
+                ctx.draw_image_sub(&img, v, 0.0, 1.0, 1.0, 0.0, 0.0, 2.0, 2.0);
+
+                ctx.push_transform();
+                ctx.rotate(v);
+                ctx.scale(v, 1.0);
+                ctx.fill_rect(0.0, 0.0, 1.0, 1.0);
+                ctx.draw_image(&img, 0.0, 0.0);
+                ctx.pop_transform();
+            }
+        }
+
+        // while sensible shapes still draw
+        ctx.clear();
+        ctx.fill_polygon(&[(0.0, 0.0), (nan, 1.0), (1.0, 3.0)], FillRule::NonZero);
+        ctx.fill_rect(0.0, 0.0, 1.0, 1.0);
+        assert_eq!(ctx.get_image_data(0, 0, 2, 1).pixels, vec![[0, 0, 0, 0xFF], [0; 4]]);
+    }
+
+    #[test]
+    fn transforms() {
+        let mut ctx = Context::headless(6, 4);
+        let (red, blue) = ([0xFF, 0, 0, 0xFF], [0, 0, 0xFF, 0xFF]);
+        let square = |ctx: &Context| ctx.get_image_data(0, 0, 6, 4).pixels.iter()
+            .map(|p| p[3] != 0).collect::<Vec<_>>();
+
+        // scaled first, then moved
+        ctx.translate(2.0, 1.0);
+        ctx.scale(2.0, 2.0);
+        assert_eq!(ctx.get_transform(), Transform::translation(2.0, 1.0) * Transform::scaling(2.0, 2.0));
+
+        ctx.fill_color((1.0, 0.0, 0.0));
+        ctx.fill_rect(0.0, 0.0, 1.0, 1.0);
+        let expected = square(&ctx);
+        assert_eq!(ctx.get_image_data(0, 1, 6, 1).pixels, vec![[0; 4], [0; 4], red, red, [0; 4], [0; 4]]);
+        assert_eq!(expected.iter().filter(|&&p| p).count(), 4);
+
+        // a quarter turn lands on the same pixels, through the path filler
+        ctx.push_transform();
+        ctx.rotate(FRAC_PI_2);
+        ctx.clear();
+        ctx.fill_rect(0.0, -1.0, 1.0, 1.0);
+        assert_eq!(square(&ctx), expected);
+        ctx.pop_transform();
+        assert_eq!(ctx.get_transform(), Transform::translation(2.0, 1.0) * Transform::scaling(2.0, 2.0));
+
+        // so do paths, images and clearing
+        ctx.clear();
+        ctx.begin_path();
+        ctx.move_to(0.0, 0.0);
+        ctx.line_to(1.0, 0.0);
+        ctx.line_to(1.0, 1.0);
+        ctx.line_to(0.0, 1.0);
+        ctx.fill();
+        assert_eq!(square(&ctx), expected);
+
+        ctx.clear();
+        ctx.draw_image(&ImageData::from_pixels(1, 1, vec![blue]), 0.0, 0.0);
+        assert_eq!(square(&ctx), expected);
+        assert_eq!(ctx.get_pixel(3, 2), Some(blue));
+
+        ctx.rotate(FRAC_PI_2);
+        ctx.draw_image(&ImageData::from_pixels(1, 1, vec![red]), 0.0, -1.0);
+        assert_eq!(square(&ctx), expected);
+        assert_eq!(ctx.get_pixel(3, 2), Some(red));
+
+        ctx.clear_rect(0.0, -1.0, 1.0, 0.5);
+        assert_eq!(ctx.get_image_data(2, 1, 2, 2).pixels, vec![red, [0; 4], red, [0; 4]]);
+
+        // pixel calls ignore the transform
+        ctx.reset_transform();
+        ctx.set_pixel(0, 0, blue);
+        assert_eq!(ctx.get_pixel(0, 0), Some(blue));
+    }
+}
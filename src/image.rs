@@ -0,0 +1,288 @@
+//! owned blocks of pixels
+//!
+//! `ImageData` is what gets read back from a `Context`, and what can be
+//! blitted onto one in bulk.
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use codec;
+use resample;
+
+/// image file formats that can be read and written
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// portable network graphics
+    Png,
+    /// binary netpbm pixmap, rgb only
+    ///
+    /// when reading, any of the netpbm formats are accepted
+    Ppm,
+    /// binary netpbm greymap, images are converted to greyscale on save
+    Pgm,
+    /// netpbm arbitrary map, stored as rgba
+    Pam,
+    /// uncompressed windows bitmap
+    Bmp,
+}
+
+impl ImageFormat {
+    /// guess the format from a file extension
+    ///
+    /// `.pbm` isn't recognized, since bitmaps can be read but not written
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let ext = path.as_ref().extension()?.to_str()?.to_lowercase();
+
+        match &ext[..] {
+            "png" => Some(ImageFormat::Png),
+            "ppm" | "pnm" => Some(ImageFormat::Ppm),
+            "pgm" => Some(ImageFormat::Pgm),
+            "pam" => Some(ImageFormat::Pam),
+            "bmp" | "dib" => Some(ImageFormat::Bmp),
+            _ => None,
+        }
+    }
+
+    /// guess the format from the first few bytes of a file
+    pub fn from_magic(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"\x89PNG") {
+            Some(ImageFormat::Png)
+        } else if bytes.starts_with(b"BM") {
+            Some(ImageFormat::Bmp)
+        } else if bytes.len() >= 2 && bytes[0] == b'P' && bytes[1] >= b'1' && bytes[1] <= b'7' {
+            Some(match bytes[1] {
+                b'2' | b'5' => ImageFormat::Pgm,
+                b'7' => ImageFormat::Pam,
+                _ => ImageFormat::Ppm,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// how images are resampled when they're drawn at a different size
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Filter {
+    /// use the closest source pixel, keeps pixel art crisp
+    Nearest,
+    /// interpolate linearly between the closest source pixels
+    Bilinear,
+    /// three lobed lanczos, for high quality scaling
+    Lanczos,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::Bilinear
+    }
+}
+
+/// anything that can be drawn onto a `Context` with `draw_image`
+pub trait ImageSource {
+    /// the pixels to draw
+    fn image_data<'a>(&'a self) -> Cow<'a, ImageData>;
+}
+
+impl ImageSource for ImageData {
+    fn image_data<'a>(&'a self) -> Cow<'a, ImageData> {
+        Cow::Borrowed(self)
+    }
+}
+
+/// the number of pixels in a `width` x `height` image
+///
+/// panics if there are more than fit in a `usize`.
+pub(crate) fn pixel_count(width: u32, height: u32) -> usize {
+    (width as usize).checked_mul(height as usize).expect("image too large to address")
+}
+
+/// a rectangle of 8 bit rgba pixels, stored in rows from top to bottom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageData {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[u8; 4]>,
+}
+
+impl ImageData {
+    /// create a new image filled with transparent black
+    ///
+    /// panics if `width * height` doesn't fit in a `usize`
+    pub fn new(width: u32, height: u32) -> Self {
+        ImageData {
+            width,
+            height,
+            pixels: vec![[0, 0, 0, 0]; pixel_count(width, height)],
+        }
+    }
+
+    /// wrap an existing buffer of pixels
+    ///
+    /// panics if `pixels` doesn't hold exactly `width * height` values
+    pub fn from_pixels(width: u32, height: u32, pixels: Vec<[u8; 4]>) -> Self {
+        assert_eq!(Some(pixels.len()), (width as usize).checked_mul(height as usize), "wrong number of pixels for image size");
+
+        ImageData { width, height, pixels }
+    }
+
+    /// dimensions
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// get the color of a single pixel, if it's inside the image
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        Some(self.pixels[y as usize * self.width as usize + x as usize])
+    }
+
+    /// set a single pixel, ignoring coordinates outside the image
+    pub fn set_pixel(&mut self, x: u32, y: u32, c: [u8; 4]) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+
+        self.pixels[y as usize * self.width as usize + x as usize] = c;
+    }
+
+    /// one row of pixels
+    pub fn row(&self, y: u32) -> &[[u8; 4]] {
+        let start = y as usize * self.width as usize;
+        &self.pixels[start .. start + self.width as usize]
+    }
+
+    /// scale the image to a new size
+    pub fn resize(&self, width: u32, height: u32, filter: Filter) -> ImageData {
+        resample::resample(self, 0.0, 0.0, self.width as f32, self.height as f32, width, height, filter)
+    }
+
+    /// encode the image as a png
+    ///
+    /// the alpha channel is only stored if the image isn't fully opaque
+    pub fn write_png<W: Write>(&self, w: W) -> io::Result<()> {
+        codec::png::encode(self, w)
+    }
+
+    /// save the image to a png file
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = File::create(path)?;
+        self.write_png(BufWriter::new(file))
+    }
+
+    /// encode the image in any of the supported formats
+    pub fn write_to<W: Write>(&self, w: W, format: ImageFormat) -> io::Result<()> {
+        match format {
+            ImageFormat::Png => codec::png::encode(self, w),
+            ImageFormat::Ppm => codec::pnm::encode_ppm(self, w),
+            ImageFormat::Pgm => codec::pnm::encode_pgm(self, w),
+            ImageFormat::Pam => codec::pnm::encode_pam(self, w),
+            ImageFormat::Bmp => codec::bmp::encode(self, w),
+        }
+    }
+
+    /// save the image, picking the format from the file extension
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let format = ImageFormat::from_path(&path).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "unknown image file extension")
+        })?;
+
+        let file = File::create(path)?;
+        self.write_to(BufWriter::new(file), format)
+    }
+
+    /// decode an image in a known format
+    pub fn read_from<R: Read>(r: R, format: ImageFormat) -> io::Result<Self> {
+        match format {
+            ImageFormat::Png => codec::png::decode(r),
+            ImageFormat::Ppm | ImageFormat::Pgm | ImageFormat::Pam => codec::pnm::decode(r),
+            ImageFormat::Bmp => codec::bmp::decode(r),
+        }
+    }
+
+    /// load an image file, detecting its format from its contents
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut data = Vec::new();
+        BufReader::new(File::open(path)?).read_to_end(&mut data)?;
+
+        let format = ImageFormat::from_magic(&data)
+            .ok_or_else(|| codec::invalid("unknown image format"))?;
+
+        ImageData::read_from(&data[..], format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pixel_count, ImageData, ImageFormat};
+
+    fn gradient(width: u32, height: u32, alpha: bool) -> ImageData {
+        let mut img = ImageData::new(width, height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let a = if alpha { (x * 40) as u8 } else { 0xFF };
+                img.set_pixel(x, y, [(x * 30) as u8, (y * 50) as u8, 0x80, a]);
+            }
+        }
+
+        img
+    }
+
+    fn roundtrip(img: &ImageData, format: ImageFormat) -> ImageData {
+        let mut buf = Vec::new();
+        img.write_to(&mut buf, format).unwrap();
+
+        assert_eq!(ImageFormat::from_magic(&buf), Some(format));
+        ImageData::read_from(&buf[..], format).unwrap()
+    }
+
+    #[test]
+    fn lossless_formats() {
+        // odd widths make sure row padding is handled
+        for &(w, h) in &[(5, 3), (1, 1), (8, 2)] {
+            let opaque = gradient(w, h, false);
+            let alpha = gradient(w, h, true);
+
+            assert_eq!(roundtrip(&opaque, ImageFormat::Ppm), opaque);
+            assert_eq!(roundtrip(&alpha, ImageFormat::Pam), alpha);
+            assert_eq!(roundtrip(&opaque, ImageFormat::Bmp), opaque);
+            assert_eq!(roundtrip(&alpha, ImageFormat::Bmp), alpha);
+            assert_eq!(roundtrip(&opaque, ImageFormat::Png), opaque);
+            assert_eq!(roundtrip(&alpha, ImageFormat::Png), alpha);
+        }
+    }
+
+    #[test]
+    fn ascii_netpbm() {
+        let pgm = b"P2\n# a comment\n2 1\n15\n0 15\n";
+        let img = ImageData::read_from(&pgm[..], ImageFormat::Pgm).unwrap();
+        assert_eq!(img.pixels, vec![[0, 0, 0, 0xFF], [0xFF, 0xFF, 0xFF, 0xFF]]);
+
+        let pbm = b"P1 3 1 101";
+        let img = ImageData::read_from(&pbm[..], ImageFormat::Ppm).unwrap();
+        assert_eq!(img.pixels, vec![[0, 0, 0, 0xFF], [0xFF, 0xFF, 0xFF, 0xFF], [0, 0, 0, 0xFF]]);
+        assert_eq!(ImageFormat::from_magic(pbm), Some(ImageFormat::Ppm));
+
+        // but never written under a bitmap extension
+        assert_eq!(ImageFormat::from_path("image.PPM"), Some(ImageFormat::Ppm));
+        assert_eq!(ImageFormat::from_path("image.pbm"), None);
+    }
+
+    #[test]
+    fn sizes_past_u32() {
+        // more pixels than a u32 can count, without allocating them
+        if cfg!(target_pointer_width = "64") {
+            assert_eq!(pixel_count(70000, 70000), 4_900_000_000);
+        }
+
+        let wide = ImageData::new(0x1_0001, 2);
+        assert_eq!(wide.row(1).len(), 0x1_0001);
+        assert_eq!(wide.get_pixel(0x1_0000, 1), Some([0; 4]));
+    }
+}
@@ -8,7 +8,7 @@
 //! let ctx = Canvas::new(640, 480);
 //!
 //! ctx.fill_color(0x0000FF);
-//! ctx.fill_rect(40, 80, 100, 200);
+//! ctx.fill_rect(40.0, 80.0, 100.0, 200.0);
 //! ctx.pause();
 //! ```
 //!
@@ -23,9 +23,22 @@ extern crate glutin;
 pub mod context;
 pub mod color;
 pub mod events;
+pub mod image;
+pub mod path;
+pub mod backend;
+pub mod testing;
+pub mod transform;
+mod codec;
+mod raster;
+mod resample;
+mod record;
 mod pipeline;
 
-pub use context::Context;
+pub use context::{Background, Context};
+pub use image::ImageData;
+pub use path::Path;
+pub use raster::{Connectivity, FillRule};
+pub use transform::Transform;
 
 
 use std::thread;
@@ -112,7 +125,6 @@ impl Canvas {
                         KeyboardInput { device_id: _, input } if is_break(input) 
                             => running = false,
                         Resized(width, height) => {
-                            self.ctx.window.update_views(gfx_window_glutin::update_views);
                             //println!("resized: ({}, {})", width, height);
                             self.ctx.resize(width, height);
 
@@ -148,7 +160,7 @@ impl Canvas {
                 }
             }
 
-            self.ctx.window.draw();
+            self.ctx.present();
             ::std::thread::sleep(::std::time::Duration::from_millis(10));
         }
     }
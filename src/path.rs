@@ -0,0 +1,869 @@
+//! reusable paths made of lines and curves, like a `Path2D` in the browser
+
+use std::f32::consts::PI;
+
+use raster;
+use transform::Transform;
+
+/// a point on the canvas
+pub type Point = (f32, f32);
+
+/// how far flattened curves may stray from the real ones, in pixels
+const TOLERANCE: f32 = 0.2;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Segment {
+    MoveTo(Point),
+    LineTo(Point),
+    CubicTo(Point, Point, Point),
+    Close,
+}
+
+/// a shape made of any number of subpaths
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Path {
+    segments: Vec<Segment>,
+    /// first point of the current subpath
+    start: Option<Point>,
+    /// where the next segment starts
+    current: Option<Point>,
+}
+
+/// a flattened subpath
+pub(crate) struct Polyline {
+    pub points: Vec<Point>,
+    pub closed: bool,
+}
+
+/// how far an arc from `start` to `end` turns, negative if anticlockwise
+fn sweep(start: f32, end: f32, anticlockwise: bool) -> f32 {
+    let tau = 2.0 * PI;
+
+    let (from, to) = if anticlockwise { (end, start) } else { (start, end) };
+    let mut sweep = if to - from >= tau { tau } else { (to - from) % tau };
+    if sweep < 0.0 {
+        sweep += tau;
+    }
+
+    if anticlockwise { -sweep } else { sweep }
+}
+
+fn lerp(a: Point, b: Point, t: f32) -> Point {
+    (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+}
+
+fn length(v: Point) -> f32 {
+    (v.0 * v.0 + v.1 * v.1).sqrt()
+}
+
+impl Path {
+    /// an empty path
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// whether nothing has been added to the path
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// start a new subpath at `(x, y)`
+    pub fn move_to(&mut self, x: f32, y: f32) {
+        self.segments.push(Segment::MoveTo((x, y)));
+        self.start = Some((x, y));
+        self.current = Some((x, y));
+    }
+
+    /// make sure there's a current point, starting a subpath at `p` if not
+    fn ensure(&mut self, p: Point) {
+        if self.current.is_none() {
+            self.move_to(p.0, p.1);
+        }
+    }
+
+    /// add a straight line to `(x, y)`
+    pub fn line_to(&mut self, x: f32, y: f32) {
+        self.ensure((x, y));
+        self.segments.push(Segment::LineTo((x, y)));
+        self.current = Some((x, y));
+    }
+
+    /// add a quadratic bézier curve with control point `(cpx, cpy)`
+    pub fn quadratic_curve_to(&mut self, cpx: f32, cpy: f32, x: f32, y: f32) {
+        self.ensure((cpx, cpy));
+        let from = self.current.unwrap();
+
+        // every quadratic is also a cubic
+        let c1 = lerp(from, (cpx, cpy), 2.0 / 3.0);
+        let c2 = lerp((x, y), (cpx, cpy), 2.0 / 3.0);
+        self.bezier_curve_to(c1.0, c1.1, c2.0, c2.1, x, y);
+    }
+
+    /// add a cubic bézier curve with control points `(cp1x, cp1y)` and `(cp2x, cp2y)`
+    pub fn bezier_curve_to(&mut self, cp1x: f32, cp1y: f32, cp2x: f32, cp2y: f32, x: f32, y: f32) {
+        self.ensure((cp1x, cp1y));
+        self.segments.push(Segment::CubicTo((cp1x, cp1y), (cp2x, cp2y), (x, y)));
+        self.current = Some((x, y));
+    }
+
+    /// add a circular arc around `(x, y)`, clockwise from `start` to `end` unless `anticlockwise`
+    pub fn arc(&mut self, x: f32, y: f32, radius: f32, start: f32, end: f32, anticlockwise: bool) {
+        let sweep = sweep(start, end, anticlockwise);
+        self.arc_sweep((x, y), radius.abs(), radius.abs(), start, sweep);
+    }
+
+    /// add part of an axis aligned ellipse around `(x, y)`, like `arc` with two radii
+    pub fn ellipse(&mut self, x: f32, y: f32, rx: f32, ry: f32, start: f32, end: f32, anticlockwise: bool) {
+        let sweep = sweep(start, end, anticlockwise);
+        self.arc_sweep((x, y), rx.abs(), ry.abs(), start, sweep);
+    }
+
+    /// add an arc of `sweep` radians, drawn with cubic curves
+    fn arc_sweep(&mut self, center: Point, rx: f32, ry: f32, start: f32, sweep: f32) {
+        let at = |a: f32| (center.0 + rx * a.cos(), center.1 + ry * a.sin());
+
+        let first = at(start);
+        if self.current.is_some() {
+            self.line_to(first.0, first.1);
+        } else {
+            self.move_to(first.0, first.1);
+        }
+
+        // no piece spans more than a quarter turn
+        let pieces = (sweep.abs() / (PI / 2.0)).ceil().max(1.0) as usize;
+        let step = sweep / pieces as f32;
+        let k = 4.0 / 3.0 * (step / 4.0).tan();
+
+        for i in 0..pieces {
+            let (a, b) = (start + step * i as f32, start + step * (i + 1) as f32);
+            let (p0, p3) = (at(a), at(b));
+
+            let c1 = (p0.0 - k * rx * a.sin(), p0.1 + k * ry * a.cos());
+            let c2 = (p3.0 + k * rx * b.sin(), p3.1 - k * ry * b.cos());
+            self.bezier_curve_to(c1.0, c1.1, c2.0, c2.1, p3.0, p3.1);
+        }
+    }
+
+    /// add an arc of the given radius that touches both the line from the
+    /// current point to `(x1, y1)` and the line from there to `(x2, y2)`
+    pub fn arc_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32) {
+        self.ensure((x1, y1));
+        let p0 = self.current.unwrap();
+
+        let v1 = (p0.0 - x1, p0.1 - y1);
+        let v2 = (x2 - x1, y2 - y1);
+        let (l1, l2) = (length(v1), length(v2));
+        let cross = v1.0 * v2.1 - v1.1 * v2.0;
+
+        if radius == 0.0 || l1 == 0.0 || l2 == 0.0 || cross.abs() < 1e-6 * l1 * l2 {
+            self.line_to(x1, y1);
+            return;
+        }
+
+        let (u1, u2) = ((v1.0 / l1, v1.1 / l1), (v2.0 / l2, v2.1 / l2));
+        let angle = (u1.0 * u2.0 + u1.1 * u2.1).max(-1.0).min(1.0).acos();
+        let radius = radius.abs();
+
+        // distance from the corner to where the arc touches each line
+        let tangent = radius / (angle / 2.0).tan();
+        let t1 = (x1 + u1.0 * tangent, y1 + u1.1 * tangent);
+        let t2 = (x1 + u2.0 * tangent, y1 + u2.1 * tangent);
+
+        let bisector = (u1.0 + u2.0, u1.1 + u2.1);
+        let scale = radius / (angle / 2.0).sin() / length(bisector);
+        let center = (x1 + bisector.0 * scale, y1 + bisector.1 * scale);
+
+        let start = (t1.1 - center.1).atan2(t1.0 - center.0);
+        let end = (t2.1 - center.1).atan2(t2.0 - center.0);
+
+        // always the short way round
+        let mut sweep = end - start;
+        if sweep > PI {
+            sweep -= 2.0 * PI;
+        } else if sweep < -PI {
+            sweep += 2.0 * PI;
+        }
+
+        self.arc_sweep(center, radius, radius, start, sweep);
+    }
+
+    /// add a closed rectangle as its own subpath
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        self.move_to(x, y);
+        self.line_to(x + width, y);
+        self.line_to(x + width, y + height);
+        self.line_to(x, y + height);
+        self.close_path();
+    }
+
+    /// add a closed rectangle with rounded corners as its own subpath
+    ///
+    /// `radii` are spread over the corners like in the browser's `roundRect`, and
+    /// scaled down together if they don't fit. other counts, or negative radii, add nothing.
+    pub fn round_rect(&mut self, x: f32, y: f32, width: f32, height: f32, radii: &[f32]) {
+        if radii.iter().any(|&r| r < 0.0 || !r.is_finite()) {
+            return;
+        }
+
+        // top left, top right, bottom right, bottom left
+        let mut r = match radii.len() {
+            1 => [radii[0]; 4],
+            2 => [radii[0], radii[1], radii[0], radii[1]],
+            3 => [radii[0], radii[1], radii[2], radii[1]],
+            4 => [radii[0], radii[1], radii[2], radii[3]],
+            _ => return,
+        };
+
+        // mirrored rectangles keep each radius at the corner it was meant for
+        let (mut x, mut y, mut width, mut height) = (x, y, width, height);
+        if width < 0.0 {
+            x += width;
+            width = -width;
+            r = [r[1], r[0], r[3], r[2]];
+        }
+        if height < 0.0 {
+            y += height;
+            height = -height;
+            r = [r[3], r[2], r[1], r[0]];
+        }
+
+        let fit = |side: f32, a: f32, b: f32| if a + b > side { side / (a + b) } else { 1.0 };
+        let scale = fit(width, r[0], r[1]).min(fit(height, r[1], r[2]))
+            .min(fit(width, r[2], r[3])).min(fit(height, r[3], r[0]));
+        for radius in &mut r {
+            *radius *= scale;
+        }
+
+        // clockwise from the top edge, as (corner, center offset, start angle)
+        let corners = [
+            ((x + width, y), (-r[1], r[1]), -PI / 2.0),
+            ((x + width, y + height), (-r[2], -r[2]), 0.0),
+            ((x, y + height), (r[3], -r[3]), PI / 2.0),
+            ((x, y), (r[0], r[0]), PI),
+        ];
+
+        self.move_to(x + r[0], y);
+        for (i, &(corner, offset, start)) in corners.iter().enumerate() {
+            let radius = r[(i + 1) % 4];
+            if radius > 0.0 {
+                let center = (corner.0 + offset.0, corner.1 + offset.1);
+                self.arc_sweep(center, radius, radius, start, PI / 2.0);
+            } else {
+                self.line_to(corner.0, corner.1);
+            }
+        }
+        self.close_path();
+    }
+
+    /// a copy of the path with every point, and so every curve, moved by `transform`
+    pub fn transformed(&self, transform: &Transform) -> Path {
+        let t = |p: Point| transform.apply(p.0, p.1);
+
+        Path {
+            segments: self.segments.iter().map(|segment| match *segment {
+                Segment::MoveTo(p) => Segment::MoveTo(t(p)),
+                Segment::LineTo(p) => Segment::LineTo(t(p)),
+                Segment::CubicTo(c1, c2, p) => Segment::CubicTo(t(c1), t(c2), t(p)),
+                Segment::Close => Segment::Close,
+            }).collect(),
+            start: self.start.map(&t),
+            current: self.current.map(&t),
+        }
+    }
+
+    /// where the next segment starts, if anywhere yet
+    pub(crate) fn current_point(&self) -> Option<Point> {
+        self.current
+    }
+
+    /// add all segments of `other` to the end of this path, with `continuing`
+    /// dropping its first move so its first subpath carries on the current one
+    pub(crate) fn extend(&mut self, other: &Path, continuing: bool) {
+        for (i, segment) in other.segments.iter().enumerate() {
+            match *segment {
+                Segment::MoveTo(_) if continuing && i == 0 => (),
+                Segment::MoveTo(p) => self.move_to(p.0, p.1),
+                Segment::LineTo(p) => self.line_to(p.0, p.1),
+                Segment::CubicTo(c1, c2, p) => self.bezier_curve_to(c1.0, c1.1, c2.0, c2.1, p.0, p.1),
+                Segment::Close => self.close_path(),
+            }
+        }
+    }
+
+    /// close the current subpath with a straight line back to its start
+    pub fn close_path(&mut self) {
+        if self.current.is_some() {
+            self.segments.push(Segment::Close);
+            self.current = self.start;
+        }
+    }
+
+    /// turn the path into polylines, replacing curves with short lines
+    pub(crate) fn flatten(&self) -> Vec<Polyline> {
+        let mut out: Vec<Polyline> = Vec::new();
+        let mut open = false;
+        let mut pen = (0.0, 0.0);
+
+        for segment in &self.segments {
+            match *segment {
+                Segment::MoveTo(p) => {
+                    out.push(Polyline { points: vec![p], closed: false });
+                    open = true;
+                    pen = p;
+                },
+                Segment::Close => {
+                    if open {
+                        let line = out.last_mut().unwrap();
+                        line.closed = true;
+                        pen = line.points[0];
+                    }
+                    open = false;
+                },
+                Segment::LineTo(p) => {
+                    if !open {
+                        out.push(Polyline { points: vec![pen], closed: false });
+                        open = true;
+                    }
+                    out.last_mut().unwrap().points.push(p);
+                    pen = p;
+                },
+                Segment::CubicTo(c1, c2, p) => {
+                    if !open {
+                        out.push(Polyline { points: vec![pen], closed: false });
+                        open = true;
+                    }
+
+                    let points = &mut out.last_mut().unwrap().points;
+                    let p0 = *points.last().unwrap();
+
+                    // enough pieces to keep the error below the tolerance
+                    let dd = |a: Point, b: Point, c: Point| length((a.0 - 2.0 * b.0 + c.0, a.1 - 2.0 * b.1 + c.1));
+                    let curvature = dd(p0, c1, c2).max(dd(c1, c2, p));
+                    let n = ((0.75 * curvature / TOLERANCE).sqrt().ceil() as usize).max(1).min(1000);
+
+                    for i in 1 ..= n {
+                        let t = i as f32 / n as f32;
+                        let (a, b, c) = (lerp(p0, c1, t), lerp(c1, c2, t), lerp(c2, p, t));
+                        points.push(lerp(lerp(a, b, t), lerp(b, c, t), t));
+                    }
+                    pen = p;
+                },
+            }
+        }
+
+        out
+    }
+
+    /// the outlines of the path for filling, with every subpath closed
+    pub(crate) fn fill_contours(&self) -> Vec<Vec<Point>> {
+        self.flatten().into_iter()
+            .map(|line| line.points)
+            .filter(|points| points.len() > 2)
+            .collect()
+    }
+
+    /// the outline of the path stroked with `style`, as polygons to be
+    /// filled with the nonzero rule
+    ///
+    /// the path is already transformed but the pen isn't, so widths and dashes
+    /// scale with `transform`. dashes are only worked out near the `visible`
+    /// box, given as its top left and bottom right corner.
+    pub(crate) fn stroke_contours(&self, style: &StrokeStyle, transform: &Transform,
+        visible: (Point, Point)) -> Vec<Vec<Point>>
+    {
+        let inverse = match transform.invert() {
+            Some(inverse) => inverse,
+            None => return Vec::new(),
+        };
+
+        let tolerance = TOLERANCE / transform.max_scale();
+        let user = |points: &[Point]| points.iter().map(|p| inverse.apply(p.0, p.1)).collect::<Vec<_>>();
+        let mut out = Vec::new();
+
+        // room for the widest the stroke can get past the visible box,
+        // miters and square caps included
+        let reach = style.width / 2.0 * transform.max_scale() * style.miter_limit.max(2.0) + 1.0;
+        let min = ((visible.0).0 - reach, (visible.0).1 - reach);
+        let max = ((visible.1).0 + reach, (visible.1).1 + reach);
+        let total: f32 = style.dash.iter().sum();
+
+        for line in self.flatten() {
+            if style.dash.is_empty() {
+                stroke_polyline(user(&line.points), line.closed, style, tolerance, &mut out);
+                continue;
+            }
+
+            for (run, closed, skipped) in visible_runs(&line.points, line.closed, min, max, &inverse) {
+                // the pattern carries on from the parts that were left out
+                let offset = ((style.dash_offset as f64 + skipped) % total as f64) as f32;
+
+                for piece in dashes(&user(&run), closed, &style.dash, offset) {
+                    stroke_polyline(piece, false, style, tolerance, &mut out);
+                }
+            }
+        }
+
+        if !transform.is_identity() {
+            for polygon in &mut out {
+                for p in polygon.iter_mut() {
+                    *p = transform.apply(p.0, p.1);
+                }
+            }
+        }
+
+        out
+    }
+}
+
+/// the parts of a polyline inside the box from `min` to `max`, with whether
+/// each is still closed and how far along it starts, measured after `inverse`
+fn visible_runs(points: &[Point], closed: bool, min: Point, max: Point, inverse: &Transform)
+    -> Vec<(Vec<Point>, bool, f64)>
+{
+    let inside = |p: &Point| p.0 >= min.0 && p.0 <= max.0 && p.1 >= min.1 && p.1 <= max.1;
+    if points.iter().all(inside) {
+        return vec![(points.to_vec(), closed, 0.0)];
+    }
+
+    let distance = |a: Point, b: Point| {
+        let (a, b) = (inverse.apply(a.0, a.1), inverse.apply(b.0, b.1));
+        (b.0 as f64 - a.0 as f64).hypot(b.1 as f64 - a.1 as f64)
+    };
+
+    let mut runs = Vec::new();
+    let mut run: Option<(Vec<Point>, f64)> = None;
+    let mut travelled = 0.0;
+
+    let count = if closed { points.len() } else { points.len().saturating_sub(1) };
+    for k in 0..count {
+        let (a, b) = (points[k], points[(k + 1) % points.len()]);
+
+        match raster::clip_line(a, b, min, max) {
+            Some([p, q]) => {
+                if p != a || run.is_none() {
+                    runs.extend(run.take());
+                    run = Some((vec![p], travelled + distance(a, p)));
+                }
+
+                if let Some(ref mut run) = run {
+                    run.0.push(q);
+                }
+                if q != b {
+                    runs.extend(run.take());
+                }
+            },
+            None => runs.extend(run.take()),
+        }
+
+        travelled += distance(a, b);
+    }
+
+    runs.extend(run);
+    runs.into_iter().map(|(points, skipped)| (points, false, skipped)).collect()
+}
+
+/// split a polyline into the dashes of a dash pattern, starting `offset` into it
+pub(crate) fn dashes(points: &[Point], closed: bool, pattern: &[f32], offset: f32) -> Vec<Vec<Point>> {
+    let total: f32 = pattern.iter().sum();
+    if points.is_empty() || !(total > 0.0) {
+        return vec![points.to_vec()];
+    }
+
+    // find where in the pattern the offset lands
+    let mut phase = offset % total;
+    if phase < 0.0 {
+        phase += total;
+    }
+    let mut i = 0;
+    while phase > 0.0 && phase >= pattern[i] {
+        phase -= pattern[i];
+        i = (i + 1) % pattern.len();
+    }
+    let mut left = (pattern[i] - phase) as f64;
+
+    // zero length dashes are kept, so round caps can turn them into dots,
+    // but dashes cut short to nothing by the end of the line are dropped
+    let keep = |dash: &[Point], zero_length: bool| zero_length || dash.iter().any(|&p| p != dash[0]);
+
+    let mut out = Vec::new();
+    let mut dash = if i % 2 == 0 { vec![points[0]] } else { Vec::new() };
+
+    let count = if closed { points.len() } else { points.len() - 1 };
+    for k in 0..count {
+        let (a, b) = (points[k], points[(k + 1) % points.len()]);
+        let len = length((b.0 - a.0, b.1 - a.1)) as f64;
+        let mut pos = 0.0;
+
+        // every dash or gap ending inside this segment. positions are kept
+        // in f64, so tiny dashes still move along long segments.
+        while len - pos >= left {
+            pos += left;
+            dash.push(lerp(a, b, if len > 0.0 { (pos / len) as f32 } else { 0.0 }));
+
+            if i % 2 == 0 {
+                let done = ::std::mem::replace(&mut dash, Vec::new());
+                if keep(&done, pattern[i] == 0.0) {
+                    out.push(done);
+                }
+            }
+
+            i = (i + 1) % pattern.len();
+            left = pattern[i] as f64;
+        }
+
+        left -= len - pos;
+        if i % 2 == 0 {
+            dash.push(b);
+        }
+    }
+
+    if i % 2 == 0 && !dash.is_empty() && keep(&dash, false) {
+        out.push(dash);
+    }
+
+    out
+}
+
+/// how the corners between two segments of a stroke are drawn
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LineJoin {
+    /// extend the outer edges until they meet, as long as the point isn't
+    /// further out than the miter limit allows
+    Miter,
+    /// round off the corner
+    Round,
+    /// cut the corner off straight
+    Bevel,
+}
+
+impl Default for LineJoin {
+    fn default() -> Self {
+        LineJoin::Miter
+    }
+}
+
+/// how the ends of open subpaths are drawn
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LineCap {
+    /// stop exactly at the end point
+    Butt,
+    /// add a half circle around the end point
+    Round,
+    /// extend past the end point by half the line width
+    Square,
+}
+
+impl Default for LineCap {
+    fn default() -> Self {
+        LineCap::Butt
+    }
+}
+
+/// everything that decides what a stroke looks like
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct StrokeStyle {
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    /// longest allowed miter, as a multiple of the line width
+    pub miter_limit: f32,
+    /// lengths of alternating dashes and gaps, always an even number of
+    /// them. empty for solid lines.
+    pub dash: Vec<f32>,
+    /// how far into the dash pattern strokes start
+    pub dash_offset: f32,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        StrokeStyle {
+            width: 1.0,
+            join: Default::default(),
+            cap: Default::default(),
+            miter_limit: 10.0,
+            dash: Vec::new(),
+            dash_offset: 0.0,
+        }
+    }
+}
+
+/// a circle as a polygon, with edges no further than `tolerance` from it
+fn circle(center: Point, radius: f32, tolerance: f32) -> Vec<Point> {
+    let steps = if radius > tolerance {
+        (PI / (1.0 - tolerance / radius).acos()).ceil().max(8.0).min(256.0) as usize
+    } else {
+        8
+    };
+
+    (0..steps).map(|i| {
+        let a = i as f32 / steps as f32 * 2.0 * PI;
+        (center.0 + radius * a.cos(), center.1 + radius * a.sin())
+    }).collect()
+}
+
+/// stroke one polyline, with a quad per segment and small polygons for corners
+/// and ends, all wound the same way so overlaps add up
+fn stroke_polyline(mut points: Vec<Point>, closed: bool, style: &StrokeStyle, tolerance: f32,
+    out: &mut Vec<Vec<Point>>)
+{
+    let half = style.width / 2.0;
+
+    points.dedup();
+    if closed && points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+
+    // a lone point only shows up through its caps
+    if points.len() == 1 {
+        let p = points[0];
+        match style.cap {
+            LineCap::Butt => (),
+            LineCap::Round => out.push(oriented(circle(p, half, tolerance))),
+            LineCap::Square => out.push(oriented(vec![
+                (p.0 - half, p.1 - half), (p.0 + half, p.1 - half),
+                (p.0 + half, p.1 + half), (p.0 - half, p.1 + half),
+            ])),
+        }
+        return;
+    }
+    if points.len() < 2 {
+        return;
+    }
+
+    let count = if closed { points.len() } else { points.len() - 1 };
+
+    // offset from the center line to the edge of each segment
+    let normals: Vec<Point> = (0..count).map(|i| {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+        let d = (b.0 - a.0, b.1 - a.1);
+        let len = length(d);
+        (-d.1 / len * half, d.0 / len * half)
+    }).collect();
+
+    for i in 0..count {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+        let n = normals[i];
+
+        out.push(oriented(vec![
+            (a.0 + n.0, a.1 + n.1), (b.0 + n.0, b.1 + n.1),
+            (b.0 - n.0, b.1 - n.1), (a.0 - n.0, a.1 - n.1),
+        ]));
+    }
+
+    let joins = if closed { count } else { count - 1 };
+    for i in 0..joins {
+        let p = points[(i + 1) % points.len()];
+        join(p, normals[i], normals[(i + 1) % count], half, style, tolerance, out);
+    }
+
+    if !closed {
+        let (first, last) = (points[0], points[points.len() - 1]);
+        cap(first, normals[0], half, style.cap, true, tolerance, out);
+        cap(last, normals[count - 1], half, style.cap, false, tolerance, out);
+    }
+}
+
+/// fill the corner at `p` between a segment with normal `n0` and the next
+/// one with normal `n1`
+fn join(p: Point, n0: Point, n1: Point, half: f32, style: &StrokeStyle, tolerance: f32,
+    out: &mut Vec<Vec<Point>>)
+{
+    // normals are the directions turned a quarter, so this is the turn
+    let cross = n0.0 * n1.1 - n0.1 * n1.0;
+    let dot = n0.0 * n1.0 + n0.1 * n1.1;
+
+    // going straight on, nothing to fill
+    if cross.abs() < 1e-6 * half * half && dot > 0.0 {
+        return;
+    }
+
+    if style.join == LineJoin::Round {
+        out.push(oriented(circle(p, half, tolerance)));
+        return;
+    }
+
+    // the gap is on the outside of the turn
+    let side = if cross > 0.0 { -1.0 } else { 1.0 };
+    let a = (p.0 + n0.0 * side, p.1 + n0.1 * side);
+    let b = (p.0 + n1.0 * side, p.1 + n1.1 * side);
+
+    // the miter point is 1 / cos(angle / 2) half widths out, where the
+    // angle is between the normals
+    let cos_half = ((1.0 + dot / (half * half)) / 2.0).max(0.0).sqrt();
+
+    if style.join == LineJoin::Miter && cos_half > 0.0 && 1.0 / cos_half <= style.miter_limit {
+        let mid = (n0.0 + n1.0, n0.1 + n1.1);
+        let scale = half / cos_half / length(mid) * side;
+        let tip = (p.0 + mid.0 * scale, p.1 + mid.1 * scale);
+
+        out.push(oriented(vec![p, a, tip, b]));
+    } else {
+        out.push(oriented(vec![p, a, b]));
+    }
+}
+
+/// add a cap at the end `p` of a stroke, whose last segment has normal `n`
+fn cap(p: Point, n: Point, half: f32, cap: LineCap, start: bool, tolerance: f32, out: &mut Vec<Vec<Point>>) {
+    match cap {
+        LineCap::Butt => (),
+        LineCap::Round => out.push(oriented(circle(p, half, tolerance))),
+        LineCap::Square => {
+            // the direction of the segment, pointing away from the stroke
+            let outward = if start { (-n.1, n.0) } else { (n.1, -n.0) };
+            let far = (p.0 + outward.0, p.1 + outward.1);
+
+            out.push(oriented(vec![
+                (p.0 + n.0, p.1 + n.1), (far.0 + n.0, far.1 + n.1),
+                (far.0 - n.0, far.1 - n.1), (p.0 - n.0, p.1 - n.1),
+            ]));
+        },
+    }
+}
+
+/// twice the signed area of a polygon
+fn area(points: &[Point]) -> f32 {
+    (0..points.len()).map(|i| {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+        a.0 * b.1 - b.0 * a.1
+    }).sum()
+}
+
+/// make a convex polygon wind clockwise on screen
+fn oriented(mut points: Vec<Point>) -> Vec<Point> {
+    if area(&points) < 0.0 {
+        points.reverse();
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::{dashes, length, LineCap, LineJoin, Path, StrokeStyle};
+    use raster::{polygon, FillRule};
+    use transform::Transform;
+
+    #[test]
+    fn arcs_stay_on_the_circle() {
+        let mut path = Path::new();
+        path.arc(10.0, 10.0, 5.0, 0.0, 1.5 * PI, false);
+
+        let line = &path.flatten()[0];
+        assert!(line.points.iter().all(|p| (length((p.0 - 10.0, p.1 - 10.0)) - 5.0).abs() < 0.02));
+
+        // clockwise goes through the bottom of the circle first
+        assert!(line.points.iter().any(|p| p.1 > 14.9));
+        let last = *line.points.last().unwrap();
+        assert!((last.0 - 10.0).abs() < 1e-4 && (last.1 - 5.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn arc_to_rounds_a_corner() {
+        let mut path = Path::new();
+        path.move_to(0.0, 0.0);
+        path.arc_to(10.0, 0.0, 10.0, 10.0, 4.0);
+        path.line_to(10.0, 10.0);
+
+        let points = &path.flatten()[0].points;
+        assert_eq!(points[1], (6.0, 0.0));
+        assert!(points.iter().all(|p| p.0 <= 10.0 + 1e-4 && p.1 >= -1e-4));
+        assert!(points.iter().any(|p| (p.0 - 10.0).abs() < 1e-4 && (p.1 - 4.0).abs() < 1e-4));
+    }
+
+    #[test]
+    fn close_starts_a_new_subpath() {
+        let mut path = Path::new();
+        path.rect(0.0, 0.0, 4.0, 4.0);
+        path.line_to(8.0, 0.0);
+
+        let lines = path.flatten();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].closed && !lines[1].closed);
+        assert_eq!(lines[1].points, vec![(0.0, 0.0), (8.0, 0.0)]);
+    }
+
+    #[test]
+    fn round_rect_radii() {
+        let corners = |x, y, width, height, radii: &[f32]| {
+            let mut path = Path::new();
+            path.round_rect(x, y, width, height, radii);
+            path.flatten().pop().map(|line| line.points)
+        };
+        let has = |points: &[(f32, f32)], p: (f32, f32)| {
+            points.iter().any(|q| (q.0 - p.0).abs() < 1e-4 && (q.1 - p.1).abs() < 1e-4)
+        };
+
+        // square top right and bottom left corners
+        let points = corners(0.0, 0.0, 10.0, 8.0, &[2.0, 0.0]).unwrap();
+        assert!(has(&points, (10.0, 0.0)) && has(&points, (0.0, 8.0)));
+        assert!(!has(&points, (0.0, 0.0)) && has(&points, (2.0, 0.0)) && has(&points, (0.0, 2.0)));
+
+        // mirrored, the top left radius ends up on the right
+        let points = corners(10.0, 0.0, -10.0, 8.0, &[2.0, 0.0, 0.0, 0.0]).unwrap();
+        assert!(has(&points, (10.0, 2.0)) && has(&points, (0.0, 0.0)));
+
+        // radii of 6 and 4 along a side of 5 are halved
+        let points = corners(0.0, 0.0, 20.0, 5.0, &[6.0, 6.0, 4.0, 4.0]).unwrap();
+        assert!(has(&points, (3.0, 0.0)) && has(&points, (20.0, 3.0)) && has(&points, (18.0, 5.0)));
+
+        assert_eq!(corners(0.0, 0.0, 4.0, 4.0, &[]), None);
+        assert_eq!(corners(0.0, 0.0, 4.0, 4.0, &[1.0, -1.0]), None);
+    }
+
+    #[test]
+    fn joins_and_caps() {
+        let mut path = Path::new();
+        path.move_to(4.0, 4.0);
+        path.line_to(12.0, 4.0);
+        path.line_to(12.0, 12.0);
+
+        let covered = |style: &StrokeStyle, x, y| {
+            let visible = ((0.0, 0.0), (20.0, 20.0));
+            polygon(&path.stroke_contours(style, &Transform::identity(), visible), FillRule::NonZero, 0, 0, 20, 20, false)
+                .iter().any(|&(px, py, _)| (px, py) == (x, y))
+        };
+
+        let mut style = StrokeStyle { width: 6.0, ..Default::default() };
+
+        // the outer corner is square with a miter, and cut off otherwise
+        assert!(covered(&style, 14, 1));
+        style.join = LineJoin::Bevel;
+        assert!(!covered(&style, 14, 1));
+        style.join = LineJoin::Miter;
+        style.miter_limit = 1.2;
+        assert!(!covered(&style, 14, 1));
+
+        // butt caps end at the end point, square ones go past it
+        assert!(!covered(&style, 3, 4));
+        style.cap = LineCap::Square;
+        assert!(covered(&style, 3, 4) && covered(&style, 12, 13));
+        assert!(!covered(&style, 0, 4));
+        style.cap = LineCap::Round;
+        assert!(covered(&style, 3, 4) && !covered(&style, 1, 1));
+    }
+
+    #[test]
+    fn dash_patterns() {
+        let line = [(0.0, 0.0), (10.0, 0.0)];
+
+        assert_eq!(dashes(&line, false, &[3.0, 1.0], 0.0), vec![
+            vec![(0.0, 0.0), (3.0, 0.0)],
+            vec![(4.0, 0.0), (7.0, 0.0)],
+            vec![(8.0, 0.0), (10.0, 0.0)],
+        ]);
+
+        // the offset moves the pattern backwards along the line
+        assert_eq!(dashes(&line, false, &[3.0, 1.0], 2.0), vec![
+            vec![(0.0, 0.0), (1.0, 0.0)],
+            vec![(2.0, 0.0), (5.0, 0.0)],
+            vec![(6.0, 0.0), (9.0, 0.0)],
+        ]);
+
+        // dashes carry on around corners
+        let corner = [(0.0, 0.0), (2.0, 0.0), (2.0, 2.0)];
+        assert_eq!(dashes(&corner, false, &[3.0, 1.0], 0.0), vec![
+            vec![(0.0, 0.0), (2.0, 0.0), (2.0, 1.0)],
+        ]);
+    }
+}
@@ -4,6 +4,9 @@ use gfx::{self, handle, texture, Device};
 use gfx_window_glutin as gfx_glutin;
 use glutin::{self, GlContext};
 
+use backend::Backend;
+use image::pixel_count;
+
 
 // backend stuff
 
@@ -30,7 +33,10 @@ struct Backing {
     data: Vec<u8>,
 }
 
-fn create_texture<F, R>(factory: &mut F, width: u32, height: u32) -> TexWithView<R>
+/// create the canvas texture
+///
+/// anything already drawn in `prev` is copied over to the new texture
+fn create_texture<F, R>(factory: &mut F, width: u32, height: u32, prev: Option<&Backing>) -> TexWithView<R>
     where F: gfx::Factory<R>, R: gfx::Resources
 {
     use gfx::texture::*;
@@ -43,10 +49,11 @@ fn create_texture<F, R>(factory: &mut F, width: u32, height: u32) -> TexWithView
             //let k = ((i + j) % 256) as u8;
             //data.extend(&[0xFF, 0, 0, 0xFF]);
 
-            if (i + j) % 2 == 0 {
-                data.extend(&[0x00, 0x00, 0x00, 0xFF]);
+            if let Some(prev) = prev.filter(|p| j < p.width && i < p.height) {
+                let k = ((i * prev.width + j) * 4) as usize;
+                data.extend(&prev.data[k .. k + 4]);
             } else {
-                data.extend(&[0xFF, 0xFF, 0xFF, 0xFF]);
+                data.extend(&[0x00, 0x00, 0x00, 0x00]);
             }
         }
     }
@@ -88,6 +95,7 @@ gfx_defines! {
     pipeline cpipe {
         vbuf: gfx::VertexBuffer<Vertex> = (),
         view: gfx::Global<[f32; 2]> = "i_View",
+        checkerboard: gfx::Global<i32> = "i_Checkerboard",
         canvas: gfx::TextureSampler<[f32; 4]> = "t_Canvas",
         out: gfx::RenderTarget<ColorFormat> = "Target0",
         depth: gfx::DepthTarget<DepthFormat> = Default::default(),
@@ -121,7 +129,7 @@ pub fn init(width: u32, height: u32, ev_loop: &glutin::EventsLoop)
     //println!("{:?}", device.get_info());
 
     //create data on the gpu
-    let (tex_backing, tex_handle, tex_view) = create_texture(&mut factory, width, height);
+    let (tex_backing, tex_handle, tex_view) = create_texture(&mut factory, width, height, None);
     let tex_sampler = factory.create_sampler({
         let mut s = texture::SamplerInfo::new(texture::FilterMethod::Scale, texture::WrapMode::Border);
         s.border = [0.0, 1.0, 0.0, 1.0].into();
@@ -144,6 +152,7 @@ pub fn init(width: u32, height: u32, ev_loop: &glutin::EventsLoop)
     let data = cpipe::Data {
         vbuf: vertex_buffer,
         view,
+        checkerboard: 1,
         canvas: (tex_view, tex_sampler),
         out: color,
         depth,
@@ -177,7 +186,7 @@ impl<D: gfx::Device, F: Factory<D::Resources>> Window<D, F> {
 
     pub fn resize(&mut self, new_width: u32, new_height: u32) {
         if new_width > self.backing.width || new_height > self.backing.height {
-            let (tb, th, tv) = create_texture(&mut self.factory, new_width, new_height);
+            let (tb, th, tv) = create_texture(&mut self.factory, new_width, new_height, Some(&self.backing));
             self.data.canvas.0 = tv;
             self._texture = th;
             self.backing = tb;
@@ -209,6 +218,16 @@ impl<D: gfx::Device, F: Factory<D::Resources>> Window<D, F> {
             <ColorFormat as Formatted>::Surface,
             ColorFormat
         >(&self._texture, None, bounds, data).expect("painting error");
+
+        // keep a copy around so the canvas can be read back
+        for j in 0..height {
+            for i in 0..width {
+                let src = data[j as usize * width as usize + i as usize];
+                let dst = ((y + j) as usize * self.backing.width as usize + (x + i) as usize) * 4;
+
+                self.backing.data[dst .. dst + 4].copy_from_slice(&src);
+            }
+        }
     }
 
     pub fn update_views<C>(&mut self, f: C) 
@@ -218,4 +237,38 @@ impl<D: gfx::Device, F: Factory<D::Resources>> Window<D, F> {
     {
         f(&self.window, &mut self.data.out, &mut self.data.depth);
     }
-}
\ No newline at end of file
+}
+
+impl Backend for GlWindow {
+    fn update_canvas(&mut self, x: u32, y: u32, width: u32, height: u32, data: &[[u8; 4]]) {
+        Window::update_canvas(self, x, y, width, height, data);
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.update_views(gfx_glutin::update_views);
+        Window::resize(self, width, height);
+    }
+
+    fn present(&mut self) {
+        self.draw();
+    }
+
+    fn read_back(&self, x: u32, y: u32, width: u32, height: u32) -> Vec<[u8; 4]> {
+        let mut out = Vec::with_capacity(pixel_count(width, height));
+
+        for j in y .. y + height {
+            for i in x .. x + width {
+                let k = (j as usize * self.backing.width as usize + i as usize) * 4;
+                let p = &self.backing.data[k .. k + 4];
+
+                out.push([p[0], p[1], p[2], p[3]]);
+            }
+        }
+
+        out
+    }
+
+    fn show_checkerboard(&mut self, show: bool) {
+        self.data.checkerboard = show as i32;
+    }
+}
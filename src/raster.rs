@@ -0,0 +1,498 @@
+//! turning shapes into pixels
+//!
+//! pixel `(x, y)` covers the square from `(x, y)` to `(x + 1, y + 1)`, so
+//! its center is at `(x + 0.5, y + 0.5)`. rasterizers report pixels along
+//! with how much of them is covered, from 0 to 1.
+
+use std::cmp::Ordering;
+
+/// a pixel and how much of it a shape covers
+pub(crate) type Coverage = (i64, i64, f32);
+
+/// the part of the line from `a` to `b` inside the box from `min` to `max`
+///
+/// clips in `f64`, measuring from the closer end, so huge coordinates keep
+/// their precision. ends inside the box are returned unchanged.
+pub(crate) fn clip_line(a: (f32, f32), b: (f32, f32), min: (f32, f32), max: (f32, f32)) -> Option<[(f32, f32); 2]> {
+    if ![a.0, a.1, b.0, b.1].iter().all(|v| v.is_finite()) {
+        return None;
+    }
+
+    let (ax, ay, bx, by) = (a.0 as f64, a.1 as f64, b.0 as f64, b.1 as f64);
+    let (dx, dy) = (bx - ax, by - ay);
+
+    // how far along the line it enters and leaves, and through which side
+    let (mut start, mut end) = ((0.0, None), (1.0, None));
+
+    // for each side, how fast the line moves towards its outside, and how
+    // far inside `a` is
+    let sides = [(-dx, ax - min.0 as f64), (dx, max.0 as f64 - ax), (-dy, ay - min.1 as f64), (dy, max.1 as f64 - ay)];
+    for (side, &(speed, inside)) in sides.iter().enumerate() {
+        if speed == 0.0 {
+            if inside < 0.0 {
+                return None;
+            }
+        } else if speed < 0.0 && inside / speed > start.0 {
+            start = (inside / speed, Some(side));
+        } else if speed > 0.0 && inside / speed < end.0 {
+            end = (inside / speed, Some(side));
+        }
+    }
+
+    if start.0 > end.0 {
+        return None;
+    }
+
+    let point = |(s, side): (f64, Option<usize>), p: (f32, f32)| {
+        let (x, y) = if s < 0.5 { (ax + dx * s, ay + dy * s) } else { (bx - dx * (1.0 - s), by - dy * (1.0 - s)) };
+
+        match side {
+            None => p,
+            Some(0) => (min.0, y as f32),
+            Some(1) => (max.0, y as f32),
+            Some(2) => (x as f32, min.1),
+            _ => (x as f32, max.1),
+        }
+    };
+
+    Some([point(start, a), point(end, b)])
+}
+
+/// the pixels whose centers lie between two coordinates, in either order,
+/// as a range from the first to one past the last
+pub(crate) fn pixel_range(a: f32, b: f32) -> (i64, i64) {
+    // far enough out for anything to be clipped, without overflowing
+    let first = |v: f32| (v - 0.5).ceil().max(-1e9).min(1e9) as i64;
+    (first(a.min(b)), first(a.max(b)))
+}
+
+/// a one pixel wide aliased line, including both end points
+///
+/// every pixel is listed, so long lines should be clipped first.
+pub(crate) fn bresenham(x0: f32, y0: f32, x1: f32, y1: f32) -> Vec<Coverage> {
+    let (mut x, mut y) = (x0.floor() as i64, y0.floor() as i64);
+    let (x1, y1) = (x1.floor() as i64, y1.floor() as i64);
+
+    let dx = (x1 - x).abs();
+    let dy = -(y1 - y).abs();
+    let sx = if x < x1 { 1 } else { -1 };
+    let sy = if y < y1 { 1 } else { -1 };
+
+    let mut err = dx + dy;
+    let mut out = Vec::with_capacity((dx - dy + 1) as usize);
+
+    loop {
+        out.push((x, y, 1.0));
+
+        if x == x1 && y == y1 {
+            return out;
+        }
+
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+}
+
+/// how to decide which parts of a self intersecting shape are inside
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FillRule {
+    /// inside wherever the outline winds around a point at all
+    NonZero,
+    /// inside wherever the outline crosses a ray from a point an odd number
+    /// of times, so overlapping parts cancel out
+    EvenOdd,
+}
+
+impl Default for FillRule {
+    fn default() -> Self {
+        FillRule::NonZero
+    }
+}
+
+impl FillRule {
+    fn inside(self, winding: i32) -> bool {
+        match self {
+            FillRule::NonZero => winding != 0,
+            FillRule::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
+/// sub-scanlines per pixel row for antialiased polygons
+const SUBSCANLINES: usize = 16;
+
+/// fill closed polygons with a scanline rasterizer, within the box from `(x0, y0)` to `(x1, y1)`
+///
+/// antialiased polygons add up exactly how much of each pixel the spans on
+/// every sub-scanline cover.
+pub(crate) fn polygon(contours: &[Vec<(f32, f32)>], rule: FillRule,
+    x0: i64, y0: i64, x1: i64, y1: i64, antialias: bool) -> Vec<Coverage>
+{
+    // edges as (top, bottom, x at top, dx per y, direction)
+    let mut edges = Vec::new();
+    for contour in contours {
+        for (i, &(ax, ay)) in contour.iter().enumerate() {
+            let (bx, by) = contour[(i + 1) % contour.len()];
+            if ay == by || !(ay.is_finite() && by.is_finite() && ax.is_finite() && bx.is_finite()) {
+                continue;
+            }
+
+            let slope = (bx - ax) / (by - ay);
+            if ay < by {
+                edges.push((ay, by, ax, slope, 1));
+            } else {
+                edges.push((by, ay, bx, slope, -1));
+            }
+        }
+    }
+
+    let n = if antialias { SUBSCANLINES } else { 1 };
+    let weight = 1.0 / n as f32;
+    let width = if x1 > x0 { (x1 - x0) as usize } else { 0 };
+
+    let mut out = Vec::new();
+    // coverage of each pixel, and changes in the coverage of every pixel
+    // from there on, so long spans don't have to touch every pixel
+    let mut cover = vec![0f32; width + 1];
+    let mut carry = vec![0f32; width + 1];
+    let mut crossings: Vec<(f32, i32)> = Vec::new();
+
+    for y in y0..y1 {
+        for j in 0..n {
+            let sy = y as f32 + (j as f32 + 0.5) * weight;
+
+            crossings.clear();
+            for &(top, bottom, x, slope, dir) in &edges {
+                if top <= sy && sy < bottom {
+                    crossings.push((x + (sy - top) * slope, dir));
+                }
+            }
+            crossings.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+            let mut winding = 0;
+            for k in 0 .. crossings.len().saturating_sub(1) {
+                winding += crossings[k].1;
+                if !rule.inside(winding) {
+                    continue;
+                }
+
+                let clamp = |x: f32| (x - x0 as f32).max(0.0).min(width as f32);
+                let (left, right) = (clamp(crossings[k].0), clamp(crossings[k + 1].0));
+
+                if !antialias {
+                    // pixel centers in [left, right)
+                    let first = |x: f32| (x - 0.5).ceil() as usize;
+                    for m in first(left) .. first(right) {
+                        cover[m] = 1.0;
+                    }
+                    continue;
+                }
+
+                if left >= right {
+                    continue;
+                }
+
+                let (l, r) = (left as usize, right as usize);
+                if l == r {
+                    cover[l] += (right - left) * weight;
+                } else {
+                    cover[l] += (l as f32 + 1.0 - left) * weight;
+                    carry[l + 1] += weight;
+                    carry[r] -= weight;
+                    cover[r] += (right - r as f32) * weight;
+                }
+            }
+        }
+
+        let mut run = 0.0;
+        for i in 0..width {
+            run += carry[i];
+            let c = (cover[i] + run).min(1.0);
+            if c > 1e-4 {
+                out.push((x0 + i as i64, y, c));
+            }
+        }
+        for v in cover.iter_mut().chain(carry.iter_mut()) {
+            *v = 0.0;
+        }
+    }
+
+    out
+}
+
+/// which neighbours of a pixel a flood fill spreads to
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Connectivity {
+    /// the pixels left, right, above and below
+    Four,
+    /// the four direct neighbours and the four diagonal ones
+    Eight,
+}
+
+impl Default for Connectivity {
+    fn default() -> Self {
+        Connectivity::Four
+    }
+}
+
+/// the connected area of matching pixels around `(x, y)`, in a `width` x
+/// `height` image, found a whole span of a row at a time
+pub(crate) fn flood<F>(width: u32, height: u32, x: u32, y: u32, connectivity: Connectivity, matches: F) -> Vec<Coverage>
+    where F: Fn(usize) -> bool
+{
+    let (width, height) = (width as i64, height as i64);
+    let (x, y) = (x as i64, y as i64);
+    if x >= width || y >= height || !matches((y * width + x) as usize) {
+        return Vec::new();
+    }
+
+    let reach = if connectivity == Connectivity::Eight { 1 } else { 0 };
+    let mut filled = vec![false; (width * height) as usize];
+    let open = |filled: &[bool], x: i64, y: i64| {
+        let i = (y * width + x) as usize;
+        !filled[i] && matches(i)
+    };
+
+    let mut out = Vec::new();
+    let mut seeds = vec![(x, y)];
+
+    while let Some((x, y)) = seeds.pop() {
+        if !open(&filled, x, y) {
+            continue;
+        }
+
+        let (mut left, mut right) = (x, x);
+        while left > 0 && open(&filled, left - 1, y) {
+            left -= 1;
+        }
+        while right + 1 < width && open(&filled, right + 1, y) {
+            right += 1;
+        }
+
+        for x in left..right + 1 {
+            filled[(y * width + x) as usize] = true;
+            out.push((x, y, 1.0));
+        }
+
+        // one seed for every run on the rows above and below that touches
+        // this span
+        let (from, to) = ((left - reach).max(0), (right + reach).min(width - 1));
+        for &row in &[y - 1, y + 1] {
+            if row < 0 || row >= height {
+                continue;
+            }
+
+            let mut in_run = false;
+            for x in from..to + 1 {
+                let hit = open(&filled, x, row);
+                if hit && !in_run {
+                    seeds.push((x, row));
+                }
+                in_run = hit;
+            }
+        }
+    }
+
+    out
+}
+
+fn fpart(x: f32) -> f32 {
+    x - x.floor()
+}
+
+fn rfpart(x: f32) -> f32 {
+    1.0 - fpart(x)
+}
+
+/// a one pixel wide antialiased line, with xiaolin wu's algorithm
+///
+/// pixels near the ends may be reported more than once.
+pub(crate) fn wu(x0: f32, y0: f32, x1: f32, y1: f32) -> Vec<Coverage> {
+    // wu's algorithm puts pixel centers on whole coordinates
+    let (mut x0, mut y0, mut x1, mut y1) = (x0 - 0.5, y0 - 0.5, x1 - 0.5, y1 - 0.5);
+
+    let steep = (y1 - y0).abs() > (x1 - x0).abs();
+    if steep {
+        ::std::mem::swap(&mut x0, &mut y0);
+        ::std::mem::swap(&mut x1, &mut y1);
+    }
+    if x0 > x1 {
+        ::std::mem::swap(&mut x0, &mut x1);
+        ::std::mem::swap(&mut y0, &mut y1);
+    }
+
+    let dx = x1 - x0;
+    let gradient = if dx == 0.0 { 1.0 } else { (y1 - y0) / dx };
+
+    let mut out = Vec::new();
+    {
+        let mut plot = |x: i64, y: i64, c: f32| {
+            if c > 0.0 {
+                out.push(if steep { (y, x, c) } else { (x, y, c) });
+            }
+        };
+
+        // first end point
+        let xend = (x0 + 0.5).floor();
+        let yend = y0 + gradient * (xend - x0);
+        let xgap = rfpart(x0 + 0.5);
+        let (xpxl1, ypxl1) = (xend as i64, yend.floor() as i64);
+        plot(xpxl1, ypxl1, rfpart(yend) * xgap);
+        plot(xpxl1, ypxl1 + 1, fpart(yend) * xgap);
+        let mut intery = yend + gradient;
+
+        // second end point
+        let xend = (x1 + 0.5).floor();
+        let yend = y1 + gradient * (xend - x1);
+        let xgap = fpart(x1 + 0.5);
+        let (xpxl2, ypxl2) = (xend as i64, yend.floor() as i64);
+        plot(xpxl2, ypxl2, rfpart(yend) * xgap);
+        plot(xpxl2, ypxl2 + 1, fpart(yend) * xgap);
+
+        for x in xpxl1 + 1 .. xpxl2 {
+            let y = intery.floor() as i64;
+            plot(x, y, rfpart(intery));
+            plot(x, y + 1, fpart(intery));
+            intery += gradient;
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::{bresenham, clip_line, flood, polygon, wu, Connectivity, FillRule};
+
+    #[test]
+    fn bresenham_is_connected() {
+        let line = bresenham(1.5, 1.5, 7.2, 3.9);
+
+        assert_eq!(line.first(), Some(&(1, 1, 1.0)));
+        assert_eq!(line.last(), Some(&(7, 3, 1.0)));
+        assert_eq!(line.len(), 7);
+
+        for w in line.windows(2) {
+            assert!((w[1].0 - w[0].0).abs() <= 1 && (w[1].1 - w[0].1).abs() <= 1);
+        }
+    }
+
+    #[test]
+    fn clipped_lines() {
+        let clip = |a, b| clip_line(a, b, (0.0, 0.0), (10.0, 10.0));
+
+        assert_eq!(clip((2.0, 3.0), (4.0, 5.0)), Some([(2.0, 3.0), (4.0, 5.0)]));
+        assert_eq!(clip((-1e30, 5.0), (1e30, 5.0)), Some([(0.0, 5.0), (10.0, 5.0)]));
+        assert_eq!(clip((5.0, 2e9), (5.0, -4.0)), Some([(5.0, 10.0), (5.0, 0.0)]));
+        assert_eq!(clip((-5.0, 0.0), (5.0, 10.0)), Some([(0.0, 5.0), (5.0, 10.0)]));
+
+        assert_eq!(clip((-5.0, 0.0), (0.0, -5.0)), None);
+        assert_eq!(clip((0.0, 0.0), (::std::f32::INFINITY, 0.0)), None);
+        assert_eq!(clip((::std::f32::NAN, 0.0), (1.0, 1.0)), None);
+    }
+
+    #[test]
+    fn wu_columns_sum_to_one() {
+        let line = wu(0.5, 2.5, 10.5, 6.0);
+
+        for x in 1..10 {
+            let total: f32 = line.iter().filter(|p| p.0 == x).map(|p| p.2).sum();
+            assert!((total - 1.0).abs() < 1e-4, "column {} has coverage {}", x, total);
+        }
+
+        // a line along pixel centers is crisp
+        let line = wu(0.5, 3.5, 6.5, 3.5);
+        assert!(line.iter().all(|p| p.1 == 3));
+    }
+
+    #[test]
+    fn fill_rules() {
+        // a pentagram, whose middle is wound around twice
+        let star: Vec<_> = (0..5).map(|i| {
+            let a = i as f32 * 4.0 * PI / 5.0 - PI / 2.0;
+            (10.0 + 9.0 * a.cos(), 10.0 + 9.0 * a.sin())
+        }).collect();
+
+        let covers = |rule, x, y| {
+            polygon(&[star.clone()], rule, 0, 0, 20, 20, false).contains(&(x, y, 1.0))
+        };
+
+        assert!(covers(FillRule::NonZero, 10, 10));
+        assert!(!covers(FillRule::EvenOdd, 10, 10));
+        assert!(covers(FillRule::EvenOdd, 10, 3));
+        assert!(!covers(FillRule::NonZero, 3, 17));
+    }
+
+    #[test]
+    fn polygon_edges_are_exact() {
+        let square = vec![(1.0, 1.0), (4.0, 1.0), (4.0, 3.0), (1.0, 3.0)];
+        let pixels = polygon(&[square.clone()], FillRule::NonZero, 0, 0, 6, 6, false);
+
+        assert_eq!(pixels.len(), 6);
+        assert!(pixels.iter().all(|&(x, y, c)| x >= 1 && x < 4 && y >= 1 && y < 3 && c == 1.0));
+
+        let half = polygon(&[square.iter().map(|p| (p.0 + 0.5, p.1)).collect()],
+            FillRule::NonZero, 0, 0, 6, 6, true);
+        assert!(half.contains(&(1, 1, 0.5)) && half.contains(&(4, 2, 0.5)));
+    }
+
+    #[test]
+    fn antialiased_coverage() {
+        let total = |pixels: &[(i64, i64, f32)]| pixels.iter().map(|p| p.2).sum::<f32>();
+
+        // the diagonal of a triangle cuts pixels it passes through in half
+        let triangle = vec![(0.0, 0.0), (4.0, 0.0), (0.0, 4.0)];
+        let pixels = polygon(&[triangle], FillRule::NonZero, 0, 0, 4, 4, true);
+        assert!(pixels.contains(&(0, 0, 1.0)));
+        assert!(pixels.iter().any(|&(x, y, c)| (x, y) == (1, 2) && (c - 0.5).abs() < 0.05));
+        assert!((total(&pixels) - 8.0).abs() < 1e-3);
+
+        // a ring, whose hole is cut out by either rule when wound the other way
+        let circle = |r: f32, dir: f32| -> Vec<(f32, f32)> {
+            (0..64).map(|i| {
+                let a = dir * i as f32 * PI / 32.0;
+                (10.3 + r * a.cos(), 10.7 + r * a.sin())
+            }).collect()
+        };
+        let area = |r: f32| 32.0 * r * r * (PI / 32.0).sin();
+
+        for &rule in &[FillRule::NonZero, FillRule::EvenOdd] {
+            let pixels = polygon(&[circle(8.0, 1.0), circle(4.0, -1.0)], rule, 0, 0, 20, 20, true);
+            assert!((total(&pixels) - (area(8.0) - area(4.0))).abs() < 0.5);
+            assert!(!pixels.iter().any(|&(x, y, _)| (x, y) == (10, 10)));
+        }
+    }
+
+    #[test]
+    fn flood_connectivity() {
+        // two blocks that only touch at a corner, and one far away
+        let image = [
+            1, 1, 0, 0, 0,
+            1, 1, 0, 0, 0,
+            0, 0, 1, 1, 0,
+            0, 1, 0, 0, 1,
+        ];
+        let fill = |connectivity| {
+            let mut pixels = flood(5, 4, 0, 0, connectivity, |i| image[i] == 1);
+            pixels.sort_by_key(|p| (p.1, p.0));
+            pixels.into_iter().map(|p| (p.0, p.1)).collect::<Vec<_>>()
+        };
+
+        assert_eq!(fill(Connectivity::Four), vec![(0, 0), (1, 0), (0, 1), (1, 1)]);
+        assert_eq!(fill(Connectivity::Eight), vec![
+            (0, 0), (1, 0), (0, 1), (1, 1), (2, 2), (3, 2), (1, 3), (4, 3),
+        ]);
+
+        // a seed outside the area fills nothing
+        assert!(flood(5, 4, 2, 0, Connectivity::Four, |i| image[i] == 1).is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+//! recording canvas sessions
+
+use std::io::{self, Write};
+use std::time::Instant;
+
+use codec::{gif, y4m};
+use image::ImageData;
+
+const NANOS: u64 = 1_000_000_000;
+
+/// where frame timestamps come from
+pub(crate) enum Clock {
+    /// wall clock time
+    Real(Instant),
+    /// every presented frame lasts exactly `1 / fps` seconds
+    Virtual { base: u64, fps: u32, frames: u64 },
+}
+
+impl Clock {
+    /// nanoseconds since the clock started
+    pub fn now(&self) -> u64 {
+        match *self {
+            Clock::Real(start) => {
+                let elapsed = start.elapsed();
+                elapsed.as_secs() * NANOS + elapsed.subsec_nanos() as u64
+            },
+            Clock::Virtual { base, fps, frames } => base + frames * NANOS / fps as u64,
+        }
+    }
+
+    /// switch to a virtual clock at `fps`, continuing from the current time
+    pub fn set_frame_rate(&mut self, fps: u32) {
+        *self = Clock::Virtual { base: self.now(), fps: fps.max(1), frames: 0 };
+    }
+
+    /// a frame was presented
+    pub fn tick(&mut self) {
+        if let Clock::Virtual { ref mut frames, .. } = *self {
+            *frames += 1;
+        }
+    }
+}
+
+/// records presented frames into an animated gif, storing only what changed
+pub(crate) struct GifRecorder {
+    start: u64,
+    /// timestamps are rounded down to multiples of this many centiseconds
+    interval: u64,
+    width: u32,
+    height: u32,
+    out: Vec<u8>,
+    /// what the animation shows after the last written frame
+    shown: Option<ImageData>,
+    /// the latest frame, and when it was captured
+    pending: Option<(ImageData, u64)>,
+}
+
+impl GifRecorder {
+    /// record frames of a `width` x `height` canvas, at most `fps` (up to 100) per second
+    pub fn new(width: u32, height: u32, fps: u32, now: u64) -> Self {
+        let (width, height) = (width.min(0xFFFF), height.min(0xFFFF));
+
+        let mut out = Vec::new();
+        gif::write_header(&mut out, width as u16, height as u16);
+
+        GifRecorder {
+            start: now,
+            interval: (100 / fps.max(1) as u64).max(1),
+            width,
+            height,
+            out,
+            shown: None,
+            pending: None,
+        }
+    }
+
+    /// size of the frames this recorder expects
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// centiseconds since the recording started, rounded down to the interval
+    fn time(&self, now: u64) -> u64 {
+        let cs = (now - self.start + NANOS / 200) / (NANOS / 100);
+        cs / self.interval * self.interval
+    }
+
+    /// add a frame presented at `now`, skipping it if nothing changed
+    pub fn capture(&mut self, frame: ImageData, now: u64) {
+        let now = self.time(now);
+
+        match self.pending.take() {
+            Some((prev, at)) => {
+                if prev == frame || at == now {
+                    // unchanged, or too soon after the last one to be shown
+                    let keep = if prev == frame { prev } else { frame };
+                    self.pending = Some((keep, at));
+                } else {
+                    self.write(&prev, now - at);
+                    self.pending = Some((frame, now));
+                }
+            },
+            None => self.pending = Some((frame, now)),
+        }
+    }
+
+    fn write(&mut self, frame: &ImageData, delay: u64) {
+        let delay = delay.min(0xFFFF) as u16;
+
+        let shown = match self.shown.take() {
+            Some(shown) => shown,
+            None => {
+                let keep = vec![false; frame.pixels.len()];
+                gif::write_frame(&mut self.out, 0, 0, frame, &keep, delay);
+                self.shown = Some(frame.clone());
+                return;
+            },
+        };
+
+        // bounding box of everything that changed
+        let (mut x0, mut y0, mut x1, mut y1) = (frame.width, frame.height, 0, 0);
+        for y in 0..frame.height {
+            for x in 0..frame.width {
+                let i = (y * frame.width + x) as usize;
+                if frame.pixels[i] != shown.pixels[i] {
+                    x0 = x0.min(x);
+                    y0 = y0.min(y);
+                    x1 = x1.max(x + 1);
+                    y1 = y1.max(y + 1);
+                }
+            }
+        }
+
+        // a frame that changed and changed back still needs its delay
+        if x0 >= x1 {
+            let (x0, y0, x1, y1) = (0, 0, 1, 1);
+            let region = ImageData::new(x1 - x0, y1 - y0);
+            gif::write_frame(&mut self.out, x0 as u16, y0 as u16, &region, &[true], delay);
+            self.shown = Some(shown);
+            return;
+        }
+
+        let mut region = ImageData::new(x1 - x0, y1 - y0);
+        let mut keep = Vec::with_capacity(region.pixels.len());
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let i = (y * frame.width + x) as usize;
+                region.pixels[keep.len()] = frame.pixels[i];
+                keep.push(frame.pixels[i] == shown.pixels[i]);
+            }
+        }
+
+        gif::write_frame(&mut self.out, x0 as u16, y0 as u16, &region, &keep, delay);
+        self.shown = Some(frame.clone());
+    }
+
+    /// write out the last frame, and return the finished gif
+    pub fn finish(mut self, now: u64) -> Vec<u8> {
+        if let Some((frame, at)) = self.pending.take() {
+            let delay = (self.time(now) - at).max(1);
+            self.write(&frame, delay);
+        }
+
+        gif::write_trailer(&mut self.out);
+        self.out
+    }
+}
+
+/// streams presented frames as constant frame rate yuv4mpeg2 video
+pub(crate) struct VideoRecorder {
+    out: Box<Write>,
+    start: u64,
+    fps: u32,
+    width: u32,
+    height: u32,
+    /// frames written so far
+    written: u64,
+    /// the latest frame, written once its slot has passed
+    pending: Option<ImageData>,
+    /// the first write error, reported when the recording stops
+    error: Option<io::Error>,
+}
+
+impl VideoRecorder {
+    pub fn new<W: Write + 'static>(mut out: W, width: u32, height: u32, fps: u32, now: u64) -> io::Result<Self> {
+        let fps = fps.max(1);
+        y4m::write_header(&mut out, width, height, fps)?;
+
+        Ok(VideoRecorder {
+            out: Box::new(out),
+            start: now,
+            fps,
+            width,
+            height,
+            written: 0,
+            pending: None,
+            error: None,
+        })
+    }
+
+    /// size of the frames this recorder expects
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// the frame slot closest to `now`
+    fn slot(&self, now: u64) -> u64 {
+        ((now - self.start) * self.fps as u64 + NANOS / 2) / NANOS
+    }
+
+    /// write the pending frame into every slot before `slot`
+    fn fill(&mut self, slot: u64) {
+        if let Some(ref frame) = self.pending {
+            while self.written < slot && self.error.is_none() {
+                if let Err(e) = y4m::write_frame(&mut self.out, frame) {
+                    self.error = Some(e);
+                }
+                self.written += 1;
+            }
+        }
+    }
+
+    /// add a frame presented at `now`
+    pub fn capture(&mut self, frame: ImageData, now: u64) {
+        let slot = self.slot(now);
+        self.fill(slot);
+        self.pending = Some(frame);
+    }
+
+    /// write out the last frame and flush the stream
+    pub fn finish(mut self, now: u64) -> io::Result<()> {
+        let end = self.slot(now).max(self.written + 1);
+        self.fill(end);
+
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => self.out.flush(),
+        }
+    }
+}
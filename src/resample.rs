@@ -0,0 +1,296 @@
+//! image scaling
+//!
+//! filters rows, then columns, on premultiplied colors
+
+use std::f32::consts::PI;
+
+use image::{Filter, ImageData};
+use raster;
+use transform::Transform;
+
+/// source pixels contributing to one destination pixel, with their weights
+type Taps = Vec<(usize, f32)>;
+
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+fn kernel(filter: Filter, x: f32) -> f32 {
+    let x = x.abs();
+
+    match filter {
+        Filter::Nearest => 1.0,
+        Filter::Bilinear => (1.0 - x).max(0.0),
+        Filter::Lanczos => if x < 3.0 { sinc(x) * sinc(x / 3.0) } else { 0.0 },
+    }
+}
+
+/// taps for each of `dst` pixels, mapped from the source span `start .. start + len`
+/// and clamped to `lo .. hi`
+fn taps(filter: Filter, start: f32, len: f32, dst: u32, lo: usize, hi: usize) -> Vec<Taps> {
+    let scale = len / dst as f32;
+
+    // lanczos widens when shrinking, so every source pixel contributes.
+    // bilinear deliberately doesn't, to match what browsers do.
+    let (support, stretch) = match filter {
+        Filter::Nearest => (0.0, 1.0),
+        Filter::Bilinear => (1.0, 1.0),
+        Filter::Lanczos => (3.0 * scale.max(1.0), scale.max(1.0)),
+    };
+
+    (0..dst).map(|i| {
+        let center = start + (i as f32 + 0.5) * scale;
+        let clamp = |j: f32| (j.max(lo as f32) as usize).min(hi - 1);
+
+        if filter == Filter::Nearest {
+            return vec![(clamp(center.floor()), 1.0)];
+        }
+
+        let first = (center - support - 0.5).floor() as i64;
+        let last = (center + support - 0.5).ceil() as i64;
+
+        let mut out: Taps = Vec::new();
+        for j in first ..= last {
+            let w = kernel(filter, (j as f32 + 0.5 - center) / stretch);
+            if w == 0.0 {
+                continue;
+            }
+
+            let j = clamp(j as f32);
+            match out.iter_mut().find(|t| t.0 == j) {
+                Some(t) => t.1 += w,
+                None => out.push((j, w)),
+            }
+        }
+
+        let total: f32 = out.iter().map(|t| t.1).sum();
+        if total == 0.0 {
+            return vec![(clamp(center.floor()), 1.0)];
+        }
+
+        for t in &mut out {
+            t.1 /= total;
+        }
+        out
+    }).collect()
+}
+
+fn premultiply(p: [u8; 4]) -> [f32; 4] {
+    let a = p[3] as f32 / 255.0;
+    [p[0] as f32 * a, p[1] as f32 * a, p[2] as f32 * a, p[3] as f32]
+}
+
+fn unpremultiply(p: [f32; 4]) -> [u8; 4] {
+    let a = p[3].max(0.0).min(255.0);
+    if a == 0.0 {
+        return [0, 0, 0, 0];
+    }
+
+    let c = |v: f32| (v * 255.0 / a).round().max(0.0).min(255.0) as u8;
+    [c(p[0]), c(p[1]), c(p[2]), a.round() as u8]
+}
+
+/// scale the `sw` x `sh` region at `(sx, sy)` of an image to `dw` x `dh` pixels
+pub(crate) fn resample(img: &ImageData, sx: f32, sy: f32, sw: f32, sh: f32,
+    dw: u32, dh: u32, filter: Filter) -> ImageData
+{
+    let (_, _, out) = place(img, (sx, sy, sw, sh), (0.0, 0.0, dw as f32, dh as f32), (dw, dh), filter);
+    out
+}
+
+/// scale the region `src` of an image onto the rectangle `dst`, both `(x, y, width, height)`
+///
+/// returns the pixels centered inside `dst` and the `clip` box, and where the first goes.
+pub(crate) fn place(img: &ImageData, src: (f32, f32, f32, f32), dst: (f32, f32, f32, f32),
+    clip: (u32, u32), filter: Filter) -> (i64, i64, ImageData)
+{
+    let (sx, sy, sw, sh) = src;
+    let (dx, dy, dw, dh) = dst;
+
+    let (x0, x1) = raster::pixel_range(dx, dx + dw);
+    let (y0, y1) = raster::pixel_range(dy, dy + dh);
+    let (x0, x1) = (x0.max(0), x1.min(clip.0 as i64));
+    let (y0, y1) = (y0.max(0), y1.min(clip.1 as i64));
+
+    if x0 >= x1 || y0 >= y1 || !(dw > 0.0 && dh > 0.0) {
+        return (x0, y0, ImageData::new(0, 0));
+    }
+
+    // the part of the source that maps onto the visible pixels
+    let (scale_x, scale_y) = (sw / dw, sh / dh);
+    let (start_x, start_y) = (sx + (x0 as f32 - dx) * scale_x, sy + (y0 as f32 - dy) * scale_y);
+    let (dw, dh) = ((x1 - x0) as u32, (y1 - y0) as u32);
+    let (len_x, len_y) = (dw as f32 * scale_x, dh as f32 * scale_y);
+
+    let mut out = ImageData::new(dw, dh);
+
+    // the part of the source region that actually lies inside the image
+    let x_lo = sx.max(0.0).floor() as usize;
+    let y_lo = sy.max(0.0).floor() as usize;
+    let x_hi = ((sx + sw).ceil().max(0.0) as usize).min(img.width as usize);
+    let y_hi = ((sy + sh).ceil().max(0.0) as usize).min(img.height as usize);
+
+    if x_lo >= x_hi || y_lo >= y_hi || !(sw > 0.0 && sh > 0.0) {
+        return (x0, y0, out);
+    }
+
+    let columns = taps(filter, start_x, len_x, dw, x_lo, x_hi);
+    let rows = taps(filter, start_y, len_y, dh, y_lo, y_hi);
+
+    // horizontal pass over every source row that's needed
+    let mut wide = vec![[0.0f32; 4]; dw as usize * (y_hi - y_lo)];
+    for y in y_lo .. y_hi {
+        let src = img.row(y as u32);
+        let dst = &mut wide[(y - y_lo) * dw as usize .. (y - y_lo + 1) * dw as usize];
+
+        for (d, taps) in dst.iter_mut().zip(&columns) {
+            for &(x, w) in taps {
+                let p = premultiply(src[x]);
+                for c in 0..4 {
+                    d[c] += p[c] * w;
+                }
+            }
+        }
+    }
+
+    // then vertically into the output
+    for (j, taps) in rows.iter().enumerate() {
+        for i in 0 .. dw as usize {
+            let mut acc = [0.0f32; 4];
+            for &(y, w) in taps {
+                let p = wide[(y - y_lo) * dw as usize + i];
+                for c in 0..4 {
+                    acc[c] += p[c] * w;
+                }
+            }
+
+            out.pixels[j * dw as usize + i] = unpremultiply(acc);
+        }
+    }
+
+    (x0, y0, out)
+}
+
+/// sample the region `src` of an image for every pixel of the canvas `area`
+/// `(x0, y0, x1, y1)`, where `to_source` maps canvas coordinates onto the image
+///
+/// unlike `place` this handles rotation and shearing, but filters at most bilinearly.
+pub(crate) fn warp(img: &ImageData, src: (f32, f32, f32, f32), to_source: &Transform,
+    area: (i64, i64, i64, i64), filter: Filter) -> ImageData
+{
+    let (sx, sy, sw, sh) = src;
+    let (x0, y0, x1, y1) = area;
+    let mut out = ImageData::new((x1 - x0).max(0) as u32, (y1 - y0).max(0) as u32);
+
+    let x_lo = sx.max(0.0).floor() as usize;
+    let y_lo = sy.max(0.0).floor() as usize;
+    let x_hi = ((sx + sw).ceil().max(0.0) as usize).min(img.width as usize);
+    let y_hi = ((sy + sh).ceil().max(0.0) as usize).min(img.height as usize);
+
+    if x_lo >= x_hi || y_lo >= y_hi {
+        return out;
+    }
+
+    let clamp = |v: f32, lo: usize, hi: usize| (v.max(lo as f32) as usize).min(hi - 1);
+    let pixel = |x: usize, y: usize| premultiply(img.row(y as u32)[x]);
+
+    for y in y0 .. y1 {
+        for x in x0 .. x1 {
+            let (u, v) = to_source.apply(x as f32 + 0.5, y as f32 + 0.5);
+            if !(u >= sx && u < sx + sw && v >= sy && v < sy + sh) {
+                continue;
+            }
+
+            let color = if filter == Filter::Nearest {
+                pixel(clamp(u.floor(), x_lo, x_hi), clamp(v.floor(), y_lo, y_hi))
+            } else {
+                let (u, v) = (u - 0.5, v - 0.5);
+                let (fu, fv) = (u - u.floor(), v - v.floor());
+                let (left, right) = (clamp(u.floor(), x_lo, x_hi), clamp(u.floor() + 1.0, x_lo, x_hi));
+                let (top, bottom) = (clamp(v.floor(), y_lo, y_hi), clamp(v.floor() + 1.0, y_lo, y_hi));
+
+                let mut acc = [0.0f32; 4];
+                for &(x, y, w) in &[(left, top, (1.0 - fu) * (1.0 - fv)), (right, top, fu * (1.0 - fv)),
+                                    (left, bottom, (1.0 - fu) * fv), (right, bottom, fu * fv)] {
+                    let p = pixel(x, y);
+                    for c in 0..4 {
+                        acc[c] += p[c] * w;
+                    }
+                }
+                acc
+            };
+
+            out.pixels[((y - y0) * (x1 - x0) + (x - x0)) as usize] = unpremultiply(color);
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{place, resample, warp};
+    use image::{Filter, ImageData};
+    use transform::Transform;
+
+    #[test]
+    fn nearest_is_exact() {
+        let img = ImageData::from_pixels(2, 1, vec![[1, 2, 3, 0xFF], [4, 5, 6, 0xFF]]);
+        let big = resample(&img, 0.0, 0.0, 2.0, 1.0, 4, 2, Filter::Nearest);
+
+        assert_eq!(big.row(0), &[[1, 2, 3, 0xFF], [1, 2, 3, 0xFF], [4, 5, 6, 0xFF], [4, 5, 6, 0xFF]]);
+        assert_eq!(big.row(0), big.row(1));
+    }
+
+    #[test]
+    fn flat_images_stay_flat() {
+        let img = ImageData::from_pixels(9, 7, vec![[10, 200, 30, 0x80]; 63]);
+
+        for &filter in &[Filter::Bilinear, Filter::Lanczos] {
+            for &(w, h) in &[(3, 2), (20, 31), (9, 7)] {
+                let out = resample(&img, 1.5, 0.0, 6.0, 7.0, w, h, filter);
+                assert!(out.pixels.iter().all(|&p| p == [10, 200, 30, 0x80]));
+            }
+        }
+    }
+
+    #[test]
+    fn crop_doesnt_bleed() {
+        let mut img = ImageData::from_pixels(4, 1, vec![[0xFF, 0, 0, 0xFF]; 4]);
+        img.set_pixel(3, 0, [0, 0, 0xFF, 0xFF]);
+
+        let out = resample(&img, 0.0, 0.0, 3.0, 1.0, 12, 1, Filter::Lanczos);
+        assert!(out.pixels.iter().all(|&p| p == [0xFF, 0, 0, 0xFF]));
+    }
+
+    #[test]
+    fn placed_between_pixels() {
+        let img = ImageData::from_pixels(2, 1, vec![[0xFF, 0, 0, 0xFF], [0, 0, 0xFF, 0xFF]]);
+
+        // half a pixel to the right, pixel centers land between the two colors
+        let (x, y, out) = place(&img, (0.0, 0.0, 2.0, 1.0), (1.5, 0.0, 2.0, 1.0), (8, 8), Filter::Bilinear);
+        assert_eq!((x, y, out.width, out.height), (1, 0, 2, 1));
+        assert_eq!(out.pixels, vec![[0xFF, 0, 0, 0xFF], [0x80, 0, 0x80, 0xFF]]);
+
+        // clipped on the left, only the second pixel is left
+        let (x, _, out) = place(&img, (0.0, 0.0, 2.0, 1.0), (-1.0, 0.0, 2.0, 1.0), (8, 8), Filter::Nearest);
+        assert_eq!((x, out.pixels), (0, vec![[0, 0, 0xFF, 0xFF]]));
+    }
+
+    #[test]
+    fn warped() {
+        let img = ImageData::from_pixels(2, 1, vec![[0xFF, 0, 0, 0xFF], [0, 0, 0xFF, 0xFF]]);
+
+        // mirrored, then moved over by a pixel; pixels outside stay transparent
+        let to_source = Transform::new(-1.0, 0.0, 0.0, 1.0, 3.0, 0.0);
+        let out = warp(&img, (0.0, 0.0, 2.0, 1.0), &to_source, (0, 0, 4, 1), Filter::Nearest);
+        assert_eq!(out.pixels, vec![[0; 4], [0, 0, 0xFF, 0xFF], [0xFF, 0, 0, 0xFF], [0; 4]]);
+
+        let out = warp(&img, (0.0, 0.0, 2.0, 1.0), &to_source, (1, 0, 3, 1), Filter::Bilinear);
+        assert_eq!(out.pixels, vec![[0, 0, 0xFF, 0xFF], [0xFF, 0, 0, 0xFF]]);
+    }
+}
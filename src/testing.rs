@@ -0,0 +1,254 @@
+//! golden image tests
+//!
+//! render something with a headless context, then compare it against a
+//! reference png stored alongside the tests:
+//!
+//! ```rust,no_run
+//! extern crate kansas;
+//! use kansas::Context;
+//! use kansas::testing::Snapshot;
+//!
+//! let mut ctx = Context::headless(64, 64);
+//! ctx.fill_color(0x0000FF);
+//! ctx.fill_rect(8.0, 8.0, 48.0, 48.0);
+//!
+//! Snapshot::new("blue_square").tolerance(2).check(&ctx);
+//! ```
+//!
+//! references live in `tests/snapshots` by default. running the tests with
+//! `KANSAS_BLESS=1` set writes the current output as the new reference
+//! instead of comparing. when a comparison fails, the actual, expected and
+//! diff images are written to a `failures` directory next to the references.
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use image::{ImageData, ImageSource};
+
+/// environment variable that turns comparisons into updates of the references
+pub const BLESS_VAR: &str = "KANSAS_BLESS";
+
+/// why a snapshot didn't match its reference
+#[derive(Debug)]
+pub enum Mismatch {
+    /// there is no reference image yet
+    Missing(PathBuf),
+    /// the reference has different dimensions
+    Size { expected: (u32, u32), actual: (u32, u32) },
+    /// too many pixels differ by more than the tolerance
+    Pixels { differing: usize, allowed: usize, max_delta: u8 },
+    /// reading or writing images failed
+    Io(io::Error),
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Mismatch::Missing(ref path) =>
+                write!(f, "no reference image at {}, run with {}=1 to create it", path.display(), BLESS_VAR),
+            Mismatch::Size { expected, actual } =>
+                write!(f, "expected a {}x{} image, got {}x{}", expected.0, expected.1, actual.0, actual.1),
+            Mismatch::Pixels { differing, allowed, max_delta } =>
+                write!(f, "{} pixels differ (at most {} allowed), by up to {}", differing, allowed, max_delta),
+            Mismatch::Io(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<io::Error> for Mismatch {
+    fn from(e: io::Error) -> Self {
+        Mismatch::Io(e)
+    }
+}
+
+/// the result of comparing two images of the same size
+#[derive(Debug, Clone)]
+pub struct Diff {
+    /// pixels with any channel off by more than the tolerance
+    pub differing: usize,
+    /// largest difference of any channel
+    pub max_delta: u8,
+    /// differing pixels in red over a faded copy of the expected image
+    pub image: ImageData,
+}
+
+/// compare two images of the same size, channel by channel
+///
+/// panics if the dimensions don't match
+pub fn diff(expected: &ImageData, actual: &ImageData, tolerance: u8) -> Diff {
+    assert_eq!(expected.dimensions(), actual.dimensions(), "can't diff images of different sizes");
+
+    let mut differing = 0;
+    let mut max_delta = 0;
+    let mut image = ImageData::new(expected.width, expected.height);
+
+    for (i, (e, a)) in expected.pixels.iter().zip(&actual.pixels).enumerate() {
+        let delta = (0..4).map(|c| (e[c] as i16 - a[c] as i16).abs() as u8).max().unwrap();
+        max_delta = max_delta.max(delta);
+
+        image.pixels[i] = if delta > tolerance {
+            differing += 1;
+            [0xFF, 0, 0, 0xFF]
+        } else {
+            let v = 0xC0 + ::codec::pnm::luma(*e) / 4;
+            [v, v, v, 0xFF]
+        };
+    }
+
+    Diff { differing, max_delta, image }
+}
+
+/// a named reference image, and how closely it has to be matched
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    name: String,
+    dir: PathBuf,
+    tolerance: u8,
+    max_diff_pixels: usize,
+}
+
+impl Snapshot {
+    /// a snapshot stored as `<name>.png` in the snapshot directory
+    ///
+    /// by default every pixel has to match exactly.
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        let root = env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from).unwrap_or_default();
+
+        Snapshot {
+            name: name.into(),
+            dir: root.join("tests").join("snapshots"),
+            tolerance: 0,
+            max_diff_pixels: 0,
+        }
+    }
+
+    /// store references in `dir` instead of `tests/snapshots`
+    pub fn dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.dir = dir.into();
+        self
+    }
+
+    /// how far each channel of a pixel may be off before it counts as different
+    pub fn tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// how many pixels may differ before the comparison fails
+    pub fn max_diff_pixels(mut self, count: usize) -> Self {
+        self.max_diff_pixels = count;
+        self
+    }
+
+    /// path of the reference image
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(format!("{}.png", self.name))
+    }
+
+    /// compare `img` against the reference, panicking if it doesn't match
+    pub fn check<I: ImageSource>(&self, img: &I) {
+        if let Err(e) = self.compare(img) {
+            panic!("snapshot `{}` doesn't match: {}", self.name, e);
+        }
+    }
+
+    /// compare `img` against the reference
+    ///
+    /// if `KANSAS_BLESS` is set, the reference is replaced instead. on a
+    /// mismatch the actual, expected and diff images are written to
+    /// `failures/` in the snapshot directory.
+    pub fn compare<I: ImageSource>(&self, img: &I) -> Result<(), Mismatch> {
+        let actual = img.image_data();
+        let path = self.path();
+
+        if env::var_os(BLESS_VAR).map_or(false, |v| v != "0" && !v.is_empty()) {
+            fs::create_dir_all(&self.dir)?;
+            actual.save_png(&path)?;
+            return Ok(());
+        }
+
+        if !path.exists() {
+            self.write_failure("actual", &actual)?;
+            return Err(Mismatch::Missing(path));
+        }
+
+        let expected = ImageData::open(&path)?;
+
+        if expected.dimensions() != actual.dimensions() {
+            self.write_failure("actual", &actual)?;
+            self.write_failure("expected", &expected)?;
+            return Err(Mismatch::Size { expected: expected.dimensions(), actual: actual.dimensions() });
+        }
+
+        let diff = diff(&expected, &actual, self.tolerance);
+
+        if diff.differing > self.max_diff_pixels {
+            self.write_failure("actual", &actual)?;
+            self.write_failure("expected", &expected)?;
+            self.write_failure("diff", &diff.image)?;
+
+            return Err(Mismatch::Pixels {
+                differing: diff.differing,
+                allowed: self.max_diff_pixels,
+                max_delta: diff.max_delta,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn write_failure(&self, kind: &str, img: &ImageData) -> io::Result<()> {
+        let dir = self.dir.join("failures");
+        fs::create_dir_all(&dir)?;
+
+        img.save_png(dir.join(format!("{}.{}.png", self.name, kind)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use super::{Mismatch, Snapshot};
+    use context::Context;
+
+    #[test]
+    fn compare_with_tolerance() {
+        if env::var_os(super::BLESS_VAR).is_some() {
+            return;
+        }
+
+        let dir = env::temp_dir().join(format!("kansas-snapshot-{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut ctx = Context::headless(8, 8);
+        ctx.fill_color((0.5, 0.5, 0.5));
+        ctx.fill_rect(0.0, 0.0, 8.0, 8.0);
+
+        let snapshot = Snapshot::new("grey").dir(&dir);
+        match snapshot.compare(&ctx) {
+            Err(Mismatch::Missing(_)) => (),
+            other => panic!("expected a missing reference, got {:?}", other),
+        }
+
+        ctx.save_png(snapshot.path()).unwrap();
+        snapshot.check(&ctx);
+
+        ctx.set_pixel(1, 1, [0x82u8, 0x80, 0x80, 0xFF]);
+        ctx.set_pixel(2, 2, [0xFFu8, 0x80, 0x80, 0xFF]);
+
+        assert!(snapshot.clone().tolerance(2).max_diff_pixels(1).compare(&ctx).is_ok());
+
+        match snapshot.clone().tolerance(2).compare(&ctx) {
+            Err(Mismatch::Pixels { differing: 1, max_delta: 0x7F, .. }) => (),
+            other => panic!("expected one differing pixel, got {:?}", other),
+        }
+        assert!(dir.join("failures").join("grey.diff.png").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
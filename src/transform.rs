@@ -0,0 +1,138 @@
+//! 2d affine transforms, mapping drawing coordinates onto canvas pixels
+
+use std::ops::Mul;
+
+/// a 2d affine transform, mapping `(x, y)` to `(a * x + c * y + e, b * x + d * y + f)`
+/// with fields named like in `DOMMatrix`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl Transform {
+    pub fn new(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) -> Self {
+        Transform { a, b, c, d, e, f }
+    }
+
+    /// the transform that leaves every point where it is
+    pub fn identity() -> Self {
+        Transform::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)
+    }
+
+    /// move every point by `(x, y)`
+    pub fn translation(x: f32, y: f32) -> Self {
+        Transform::new(1.0, 0.0, 0.0, 1.0, x, y)
+    }
+
+    /// turn every point around the origin, clockwise on screen in radians
+    pub fn rotation(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Transform::new(cos, sin, -sin, cos, 0.0, 0.0)
+    }
+
+    /// stretch every point away from the origin, by `x` horizontally and
+    /// `y` vertically
+    pub fn scaling(x: f32, y: f32) -> Self {
+        Transform::new(x, 0.0, 0.0, y, 0.0, 0.0)
+    }
+
+    /// this transform, followed by `other`
+    pub fn then(&self, other: &Transform) -> Transform {
+        Transform {
+            a: other.a * self.a + other.c * self.b,
+            b: other.b * self.a + other.d * self.b,
+            c: other.a * self.c + other.c * self.d,
+            d: other.b * self.c + other.d * self.d,
+            e: other.a * self.e + other.c * self.f + other.e,
+            f: other.b * self.e + other.d * self.f + other.f,
+        }
+    }
+
+    /// where `(x, y)` ends up
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        (self.a * x + self.c * y + self.e, self.b * x + self.d * y + self.f)
+    }
+
+    /// the transform that undoes this one, unless it squashes everything
+    /// onto a line or a point
+    pub fn invert(&self) -> Option<Transform> {
+        let det = self.a * self.d - self.b * self.c;
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+
+        Some(Transform {
+            a: self.d / det,
+            b: -self.b / det,
+            c: -self.c / det,
+            d: self.a / det,
+            e: (self.c * self.f - self.d * self.e) / det,
+            f: (self.b * self.e - self.a * self.f) / det,
+        })
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Transform::identity()
+    }
+
+    /// whether horizontal and vertical lines stay that way
+    pub(crate) fn is_axis_aligned(&self) -> bool {
+        self.b == 0.0 && self.c == 0.0
+    }
+
+    /// the most any length gets stretched
+    pub(crate) fn max_scale(&self) -> f32 {
+        let sum = self.a * self.a + self.b * self.b + self.c * self.c + self.d * self.d;
+        let det = self.a * self.d - self.b * self.c;
+
+        ((sum + (sum * sum - 4.0 * det * det).max(0.0).sqrt()) / 2.0).sqrt()
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+/// `a * b` applies `b` first, then `a`, like multiplying matrices
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, rhs: Transform) -> Transform {
+        rhs.then(&self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::Transform;
+
+    fn close(a: (f32, f32), b: (f32, f32)) -> bool {
+        (a.0 - b.0).abs() < 1e-5 && (a.1 - b.1).abs() < 1e-5
+    }
+
+    #[test]
+    fn composition() {
+        let t = Transform::scaling(2.0, 3.0).then(&Transform::translation(10.0, 20.0));
+        assert_eq!(t.apply(1.0, 1.0), (12.0, 23.0));
+        assert_eq!(Transform::translation(10.0, 20.0) * Transform::scaling(2.0, 3.0), t);
+
+        // a quarter turn clockwise on screen
+        assert!(close(Transform::rotation(FRAC_PI_2).apply(1.0, 0.0), (0.0, 1.0)));
+
+        let t = t.then(&Transform::rotation(0.3)).then(&Transform::new(1.0, 0.5, -0.2, 1.0, 3.0, 4.0));
+        let inverse = t.invert().unwrap();
+        assert!(close(inverse.apply(t.apply(5.0, -7.0).0, t.apply(5.0, -7.0).1), (5.0, -7.0)));
+        assert_eq!(Transform::scaling(0.0, 1.0).invert(), None);
+
+        assert!((Transform::scaling(2.0, 3.0).then(&Transform::rotation(1.0)).max_scale() - 3.0).abs() < 1e-5);
+    }
+}
@@ -1,13 +1,20 @@
 #version 330
 
 uniform sampler2D t_Canvas;
+uniform int i_Checkerboard;
 
 in vec2 v_UV;
 out vec4 Target0;
 
 void main() {
-    vec3 canvas = texture(t_Canvas, v_UV).rgb;
+    vec4 canvas = texture(t_Canvas, v_UV);
 
-    Target0 = vec4(canvas, 1.0);
-}
+    // transparent parts show black, or a grey checkerboard of 8px squares
+    vec3 backdrop = vec3(0.0);
+    if (i_Checkerboard != 0) {
+        ivec2 square = ivec2(gl_FragCoord.xy) / 8;
+        backdrop = (square.x + square.y) % 2 == 0 ? vec3(0.4) : vec3(0.6);
+    }
 
+    Target0 = vec4(mix(backdrop, canvas.rgb, canvas.a), 1.0);
+}
@@ -0,0 +1,46 @@
+extern crate kansas;
+
+use kansas::Context;
+use kansas::testing::Snapshot;
+
+#[test]
+fn color_wheel() {
+    let mut ctx = Context::headless(320, 240);
+    let wheel: [u32; 6] = [0xFF0000, 0xFFFF00, 0x00FF00,
+                           0x00FFFF, 0x0000FF, 0xFF00FF];
+
+    ctx.fill_color(0xFFFFFF);
+    ctx.fill_rect(0.0, 0.0, 320.0, 240.0);
+
+    for (i, color) in wheel.iter().enumerate() {
+        let i = i as f32;
+
+        ctx.fill_color(*color);
+        ctx.fill_rect(30.0 * i + 10.0, 20.0 * i + 10.0, 100.0, 100.0);
+    }
+
+    Snapshot::new("color_wheel").check(&ctx);
+}
+
+#[test]
+fn scaled_offscreen() {
+    let mut ctx = Context::headless(64, 64);
+    ctx.fill_color((0.2, 0.2, 0.2));
+    ctx.fill_rect(0.0, 0.0, 64.0, 64.0);
+
+    let mut sprite = ctx.create_offscreen(4, 4);
+    sprite.fill_by(0, 0, 4, 4, |x, y| {
+        if (x + y) % 3 == 0 { (1.0, 0.5, 0.0, 1.0) } else { (0.0, 0.0, 0.0, 0.0) }
+    });
+    sprite.set_pixel(0, 3, [0u8, 0x80, 0xFF, 0xFF]);
+
+    ctx.draw_image_scaled(&sprite, 4.0, 4.0, 24.0, 24.0);
+
+    ctx.image_filter(kansas::image::Filter::Nearest);
+    ctx.draw_image_scaled(&sprite, 36.0, 4.0, 24.0, 24.0);
+
+    ctx.image_filter(kansas::image::Filter::Lanczos);
+    ctx.draw_image_sub(&sprite, 0.0, 0.0, 2.0, 4.0, 4.0, 36.0, 56.0, 24.0);
+
+    Snapshot::new("scaled_offscreen").tolerance(1).check(&ctx);
+}